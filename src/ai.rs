@@ -0,0 +1,57 @@
+use crate::core::{Game, TickOutcome};
+use crate::shape::Rotation;
+
+/// The reachable placement that clears the most lines, then scores the most. When
+/// nothing clears, the one leaving the fewest holes and the lowest stack wins.
+/// Returns the rotation and the topleft column to drop from.
+pub fn best_scoring_move(game: &Game) -> Option<(Rotation, i32)> {
+    game.reachable_placements()
+        .into_iter()
+        .filter_map(|(rotation, topleft)| {
+            let mut placed = game.clone();
+            placed.tetromino.current_rotation = rotation;
+            placed.tetromino.topleft = topleft;
+            let lines = match placed.lock_now() {
+                Ok(TickOutcome::LinesCleared(rows)) => rows.len(),
+                Ok(TickOutcome::GameOver) | Err(_) => return None,
+                Ok(_) => 0,
+            };
+            let survival = (placed.count_holes(), placed.max_height());
+            Some(((lines, placed.score()), survival, (rotation, topleft.x)))
+        })
+        .max_by(|(score_a, survival_a, _), (score_b, survival_b, _)| {
+            score_a.cmp(score_b).then(survival_b.cmp(survival_a))
+        })
+        .map(|(_, _, placement)| placement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Block, PLAYGROUND_HEIGHT, PLAYGROUND_WIDTH};
+    use crate::randomizer::Randomizer;
+    use crate::shape::Shape;
+    use crate::tetromino::Tetromino;
+
+    #[test]
+    fn best_scoring_move_clears_line() {
+        let mut game = Game::with_seed(Randomizer::Random, 5);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1] =
+            [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(0, None);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+
+        assert_eq!(best_scoring_move(&game), Some((8738, -2)));
+    }
+
+    #[test]
+    fn best_scoring_move_survives_without_clear() {
+        let mut game = Game::with_seed(Randomizer::Random, 5);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 8738);
+
+        assert_eq!(
+            best_scoring_move(&game).map(|(rotation, _)| rotation),
+            Some(240)
+        );
+    }
+}
@@ -1,119 +1,2852 @@
+use crate::randomizer::{Generator, Randomizer};
+use crate::shape::{Rotation, Shape};
 use crate::tetromino::Tetromino;
-use crate::ui::{curses_teardown, Color};
+use crate::ui::Color;
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+use std::rc::Rc;
 
 pub const PLAYGROUND_WIDTH: i32 = 10;
 pub const PLAYGROUND_HEIGHT: i32 = 16;
+pub const POINTS_PER_ROW: u64 = PLAYGROUND_WIDTH as u64;
+/// Ticks between two gravity steps.
+pub const GRAVITY_TICKS: u8 = 5;
+/// Placements `Game::pc_reachable` tries before giving up.
+pub const PC_SEARCH_BUDGET: usize = 50_000;
 
+#[derive(Clone)]
 pub struct Game {
     pub grid: Grid,
     pub tetromino: Tetromino,
     pub next_tetromino: Tetromino,
-    pub paused: bool,
-    pub score: u64,
+    pub visible_next: usize,
+    pub soft_drop: SoftDropSpeed,
+    pub clear_gravity: ClearGravity,
+    /// Filled cells that make a row clear.
+    pub clear_threshold: u32,
+    /// Highest score the game will award, for leaderboards that cap it.
+    pub score_cap: Option<u64>,
+    /// Hides landed blocks from rendering. They still collide.
+    pub invisible: bool,
+    /// Locks the tetromino on hard drop. Without it the piece can still slide until
+    /// gravity locks it.
+    pub hard_drop_locks: bool,
+    pub simultaneous_input: SimultaneousInput,
+    /// Ticks a new tetromino spends entering, e.g. fading in, before gravity moves it.
+    pub spawn_anim_frames: u32,
+    /// Ticks the game holds still after a clear, with no gravity and no input.
+    pub clear_delay_frames: u32,
+    horizontal_wrap: bool,
+    gravity_dir: GravityDir,
+    paused: bool,
+    over: bool,
+    score: u64,
+    breakdown: ScoreBreakdown,
+    cap_event: bool,
     counter: u8,
+    piece_age: u32,
+    clear_delay: u32,
+    hole_delta: i32,
+    chain: u32,
+    restart_point: Option<Rc<Game>>,
+    generator: Generator,
 }
 
 impl Game {
     pub fn new() -> Game {
+        Game::with_randomizer(Randomizer::Random)
+    }
+
+    pub fn with_randomizer(randomizer: Randomizer) -> Game {
+        Game::with_generator(Generator::new(randomizer))
+    }
+
+    pub fn with_seed(randomizer: Randomizer, seed: u64) -> Game {
+        Game::with_generator(Generator::seeded(randomizer, seed))
+    }
+
+    fn with_generator(mut generator: Generator) -> Game {
         let grid = Game::create_grid();
         Game {
-            tetromino: Tetromino::new(grid),
-            next_tetromino: Tetromino::new(grid),
+            tetromino: generator.next_tetromino(grid),
+            next_tetromino: generator.next_tetromino(grid),
+            generator,
             grid,
+            visible_next: 1,
+            soft_drop: SoftDropSpeed::Cells(1),
+            clear_gravity: ClearGravity::Naive,
+            clear_threshold: PLAYGROUND_WIDTH as u32,
+            score_cap: None,
+            invisible: false,
+            hard_drop_locks: true,
+            simultaneous_input: SimultaneousInput::Cancel,
+            spawn_anim_frames: 0,
+            clear_delay_frames: 0,
+            clear_delay: 0,
+            horizontal_wrap: false,
+            gravity_dir: GravityDir::Down,
             score: 0,
+            breakdown: ScoreBreakdown::default(),
+            cap_event: false,
             counter: 0,
+            piece_age: 0,
             paused: false,
+            over: false,
+            hole_delta: 0,
+            chain: 0,
+            restart_point: None,
+        }
+    }
+
+    /// A seed that depends only on the date, so everyone gets the same pieces that day.
+    pub fn daily_seed(year: i32, month: u32, day: u32) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let date = year
+            .to_le_bytes()
+            .iter()
+            .chain(month.to_le_bytes().iter())
+            .chain(day.to_le_bytes().iter())
+            .copied()
+            .collect::<Vec<u8>>();
+        for byte in date {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+
+    pub fn score_breakdown(&self) -> ScoreBreakdown {
+        self.breakdown
+    }
+
+    #[cfg(test)]
+    fn counter(&self) -> u8 {
+        self.counter
+    }
+
+    pub fn create_grid() -> Grid {
+        [Game::create_empty_row(); PLAYGROUND_HEIGHT as usize]
+    }
+
+    fn create_empty_row() -> Row {
+        [Block::new(0, None); PLAYGROUND_WIDTH as usize]
+    }
+
+    // A threshold of zero would clear empty rows forever, so at least one cell counts.
+    fn is_full(row: &Row, threshold: u32) -> bool {
+        Game::fill_count(row) >= threshold.max(1)
+    }
+
+    fn fill_count(row: &Row) -> u32 {
+        row.iter().filter(|block| block.value != 0).count() as u32
+    }
+
+    pub fn row_fill_counts(&self) -> [u32; PLAYGROUND_HEIGHT as usize] {
+        let mut counts = [0; PLAYGROUND_HEIGHT as usize];
+        for (count, row) in counts.iter_mut().zip(self.grid.iter()) {
+            *count = Game::fill_count(row);
+        }
+        counts
+    }
+
+    /// The grid rows as they would be after `clear_rows`, without clearing them.
+    pub fn rows_after_clear(&self) -> Vec<Row> {
+        let mut cleared = self.clone();
+        cleared.clear_rows();
+        cleared.grid.to_vec()
+    }
+
+    pub fn full_rows(&self) -> Vec<usize> {
+        (0..self.grid.len())
+            .filter(|row| Game::is_full(&self.grid[*row], self.clear_threshold))
+            .collect()
+    }
+
+    /// Clears full rows and collapses the stack until no full row is left. Each step
+    /// of the chain scores its rows times the step number. Returns the rows cleared at
+    /// each step, as they were on the board of that step. Under sideways gravity,
+    /// completely filled columns clear instead.
+    pub fn clear_rows(&mut self) -> Vec<usize> {
+        let cleared = match self.gravity_dir {
+            GravityDir::Down => self.clear_rows_down(),
+            GravityDir::Up => {
+                self.grid.reverse();
+                let rows = self.clear_rows_down();
+                self.grid.reverse();
+                rows.into_iter()
+                    .map(|row| PLAYGROUND_HEIGHT as usize - 1 - row)
+                    .collect()
+            }
+            GravityDir::Left | GravityDir::Right => self.clear_columns(),
+        };
+        self.tetromino.grid = self.grid;
+        cleared
+    }
+
+    fn clear_rows_down(&mut self) -> Vec<usize> {
+        let mut cleared = Vec::new();
+        self.chain = 0;
+        loop {
+            let rows = self.full_rows();
+            let full = rows.len();
+            if full == 0 {
+                break;
+            }
+            self.chain += 1;
+            self.grid = collapse(&self.grid, self.clear_gravity, self.clear_threshold);
+            let base = POINTS_PER_ROW * full as u64;
+            self.breakdown.line_clears += self.add_score(base);
+            self.breakdown.chains += self.add_score(base * (self.chain as u64 - 1));
+            cleared.extend(rows);
+        }
+        cleared
+    }
+
+    /// Clears full columns in one step and shifts the rest towards the gravity.
+    fn clear_columns(&mut self) -> Vec<usize> {
+        let columns = (0..PLAYGROUND_WIDTH as usize)
+            .filter(|&x| self.grid.iter().all(|row| row[x].value != 0))
+            .collect::<Vec<usize>>();
+        self.chain = 0;
+        if columns.is_empty() {
+            return columns;
+        }
+        self.chain = 1;
+        let start = match self.gravity_dir {
+            GravityDir::Right => columns.len(),
+            _ => 0,
+        };
+        for row in self.grid.iter_mut() {
+            let kept = (0..PLAYGROUND_WIDTH as usize)
+                .filter(|x| !columns.contains(x))
+                .map(|x| row[x])
+                .collect::<Vec<Block>>();
+            *row = Game::create_empty_row();
+            row[start..start + kept.len()].copy_from_slice(&kept);
+        }
+        let points = self.add_score(POINTS_PER_ROW * columns.len() as u64);
+        self.breakdown.line_clears += points;
+        columns
+    }
+
+    /// Collapse steps that cleared rows during the last clear.
+    pub fn last_chain(&self) -> u32 {
+        self.chain
+    }
+
+    /// Clears every group of at least `min_group` connected blocks of one color, one
+    /// point per block, then lets the rest cascade down. Returns the points awarded.
+    pub fn clear_color_groups(&mut self, min_group: usize) -> u64 {
+        let mut seen = [[false; PLAYGROUND_WIDTH as usize]; PLAYGROUND_HEIGHT as usize];
+        let mut cleared = Vec::new();
+        for y in 0..PLAYGROUND_HEIGHT as usize {
+            for x in 0..PLAYGROUND_WIDTH as usize {
+                let block = self.grid[y][x];
+                if block.value == 0 || block.color.is_none() || seen[y][x] {
+                    continue;
+                }
+                let group = flood(&self.grid, (y, x), &mut seen, |a, b| a.color == b.color);
+                if group.len() >= min_group.max(1) {
+                    cleared.extend(group);
+                }
+            }
+        }
+        for &(y, x) in cleared.iter() {
+            self.grid[y][x] = Block::new(0, None);
+        }
+        while settle_step(&mut self.grid) {}
+        self.tetromino.grid = self.grid;
+        self.next_tetromino.grid = self.grid;
+
+        let points = self.add_score(cleared.len() as u64);
+        self.breakdown.color_groups += points;
+        points
+    }
+
+    /// Removes the bottom rows whether or not they are full, e.g. for a bomb power-up.
+    pub fn clear_bottom_n(&mut self, n: u32) -> u64 {
+        let n = (n as usize).min(self.grid.len());
+        self.grid.rotate_right(n);
+        for row in self.grid[..n].iter_mut() {
+            *row = Game::create_empty_row();
+        }
+        self.tetromino.grid = self.grid;
+        let points = self.add_score(POINTS_PER_ROW * n as u64);
+        self.breakdown.bombs += points;
+        points
+    }
+
+    /// Returns the points actually awarded after saturation and the cap.
+    fn add_score(&mut self, points: u64) -> u64 {
+        let before = self.score;
+        self.score = self.score.saturating_add(points);
+        if let Some(cap) = self.score_cap {
+            self.score = self.score.min(cap);
+            if before < cap && self.score >= cap {
+                self.cap_event = true;
+            }
+        }
+        self.score - before
+    }
+
+    pub fn score_cap_reached(&self) -> bool {
+        self.score_cap.is_some_and(|cap| self.score >= cap)
+    }
+
+    /// Whether the score has reached the cap since the last call. Each time the score
+    /// reaches the cap, this returns `true` once.
+    pub fn take_score_cap_event(&mut self) -> bool {
+        std::mem::take(&mut self.cap_event)
+    }
+
+    pub fn tick(&mut self, input: Option<Input>) -> Result<TickOutcome, &'static str> {
+        if self.over {
+            return Err("Game over.");
+        }
+        let delayed = self.in_clear_delay();
+        let outcome = self.handle_falling();
+        if delayed {
+            return Ok(outcome);
+        }
+        if let (Some(input), false) = (input, self.over) {
+            // Report the hard drop's own lock rather than the gravity step before it,
+            // unless gravity already locked the piece it was meant for.
+            if input == Input::HardDrop && self.hard_drop_locks {
+                if outcome != TickOutcome::Falling {
+                    return Ok(outcome);
+                }
+                self.tetromino.move_all_the_way_down();
+                return Ok(self.lock());
+            }
+            self.apply(input)?;
+        }
+        Ok(outcome)
+    }
+
+    /// Piece inputs that would succeed right now. A hard drop always does unless it
+    /// tops out.
+    pub fn legal_inputs(&self) -> Vec<Input> {
+        if self.over {
+            return Vec::new();
+        }
+        [
+            Input::Left,
+            Input::Right,
+            Input::SoftDrop,
+            Input::RotateLeft,
+            Input::RotateRight,
+            Input::HardDrop,
+        ]
+        .iter()
+        .copied()
+        .filter(|input| self.clone().apply(*input).is_ok())
+        .collect()
+    }
+
+    /// Advances one tick with no input. Gravity counts whole ticks, so a seed and its
+    /// inputs replay identically however fast the frontend runs them.
+    pub fn simulate_tick(&mut self) -> TickOutcome {
+        if self.over {
+            return TickOutcome::GameOver;
+        }
+        self.handle_falling()
+    }
+
+    pub fn apply(&mut self, input: Input) -> Result<(), &'static str> {
+        if self.over {
+            return Err("Game over.");
+        }
+        match input {
+            Input::Left => self.tetromino.move_sideways(Direction::Left),
+            Input::Right => self.tetromino.move_sideways(Direction::Right),
+            Input::SoftDrop => self.soft_drop(),
+            Input::RotateLeft => self.tetromino.rotate(Direction::Left),
+            Input::RotateRight => self.tetromino.rotate(Direction::Right),
+            Input::HardDrop => {
+                self.tetromino.move_all_the_way_down();
+                if self.hard_drop_locks {
+                    self.lock();
+                }
+                // A training restart keeps the game going, so only a real top-out fails.
+                if self.over {
+                    return Err("Game over.");
+                }
+                Ok(())
+            }
+            Input::ClearBoard => {
+                self.clear_board();
+                Ok(())
+            }
+        }
+    }
+
+    /// The horizontal move for the directions held down, oldest press first. When both
+    /// are held, `simultaneous_input` decides.
+    pub fn horizontal_input(&self, held: &[Input]) -> Option<Input> {
+        let mut pressed = held
+            .iter()
+            .copied()
+            .filter(|input| *input == Input::Left || *input == Input::Right);
+        let first = pressed.next()?;
+        match pressed.find(|input| *input != first) {
+            None => Some(first),
+            Some(last) => match self.simultaneous_input {
+                SimultaneousInput::Cancel => None,
+                SimultaneousInput::FirstPressed => Some(first),
+                SimultaneousInput::LastPressed => Some(last),
+            },
+        }
+    }
+
+    /// The game as it would be after `input`, leaving `self` untouched. A rejected input
+    /// yields an unchanged copy.
+    pub fn peek_apply(&self, input: Input) -> Game {
+        let mut game = self.clone();
+        let _ = game.apply(input);
+        game
+    }
+
+    /// Applies inputs in order without any gravity in between. A hard drop always locks
+    /// the tetromino here, even without `hard_drop_locks`, so whole placements can be
+    /// scripted.
+    pub fn run_inputs(&mut self, inputs: &[Input]) -> Result<(), &'static str> {
+        for &input in inputs {
+            self.apply(input)?;
+            if input == Input::HardDrop && !self.hard_drop_locks {
+                self.lock_now()?;
+            }
+            if self.over {
+                return Err("Game over.");
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns the landed grid upside down, lifting the falling tetromino out of the
+    /// way if needed. The game is over if there is no room left for it.
+    pub fn flip_vertical(&mut self) {
+        self.grid.reverse();
+        self.tetromino.grid = self.grid;
+        self.next_tetromino.grid = self.grid;
+        while !self.tetromino.fits() && self.tetromino.topleft.y > 0 {
+            self.tetromino.topleft.y -= 1;
+        }
+        if !self.tetromino.fits() {
+            self.over = true;
+        }
+    }
+
+    fn soft_drop(&mut self) -> Result<(), &'static str> {
+        match self.soft_drop {
+            SoftDropSpeed::Cells(0) => Ok(()),
+            SoftDropSpeed::Cells(cells) => {
+                self.tetromino.move_down()?;
+                for _ in 1..cells {
+                    if self.tetromino.move_down().is_err() {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            SoftDropSpeed::Instant => {
+                self.tetromino.move_all_the_way_down();
+                Ok(())
+            }
+        }
+    }
+
+    pub fn save_board(&self) -> Grid {
+        self.grid
+    }
+
+    /// Puts back a board from `save_board`, leaving the pieces and the randomizer alone.
+    pub fn restore_board(&mut self, grid: Grid) {
+        self.grid = grid;
+        self.tetromino.grid = self.grid;
+        self.next_tetromino.grid = self.grid;
+    }
+
+    pub fn clear_board(&mut self) {
+        self.grid = Game::create_grid();
+        self.tetromino.grid = self.grid;
+        self.next_tetromino.grid = self.grid;
+    }
+
+    pub fn handle_falling(&mut self) -> TickOutcome {
+        let mut outcome = TickOutcome::Falling;
+        if self.in_clear_delay() {
+            self.clear_delay -= 1;
+            return outcome;
+        }
+        let animating = self.in_spawn_animation();
+        self.piece_age += 1;
+        if animating {
+            return outcome;
+        }
+        self.counter += 1;
+        if self.counter == GRAVITY_TICKS {
+            if self.tetromino.move_down().is_err() {
+                outcome = self.lock();
+            }
+            self.counter = 0;
+        }
+        outcome
+    }
+
+    /// Lands the falling tetromino where it is, without dropping it first.
+    pub fn lock_now(&mut self) -> Result<TickOutcome, &'static str> {
+        if self.over {
+            return Err("Game over.");
+        }
+        if !self.tetromino.fits() {
+            return Err("Invalid position.");
+        }
+        Ok(self.lock())
+    }
+
+    /// Applies the board preset. With `auto_restart`, topping out puts the game back
+    /// to this point, with the same pieces still to come.
+    pub fn set_training(&mut self, config: TrainingConfig) {
+        if let Some(board) = config.initial_board {
+            self.grid = board;
+            self.tetromino.grid = self.grid;
+            self.next_tetromino.grid = self.grid;
+        }
+        self.restart_point = None;
+        if config.auto_restart {
+            self.restart_point = Some(Rc::new(self.clone()));
+        }
+    }
+
+    pub fn spawn_offset(&self) -> i32 {
+        self.generator.spawn_offset()
+    }
+
+    /// Spawns tetrominos `offset` columns away from their shape's spawn column, or as
+    /// far as the walls and landed blocks allow. The falling and next tetrominos are
+    /// put back at their spawn positions with the new offset.
+    pub fn set_spawn_offset(&mut self, offset: i32) {
+        self.generator.set_spawn_offset(offset);
+        self.respawn();
+    }
+
+    /// Puts the falling and next tetrominos back where they spawn.
+    fn respawn(&mut self) {
+        self.tetromino = self.spawn(&self.tetromino);
+        self.next_tetromino = self.spawn(&self.next_tetromino);
+    }
+
+    /// A fresh `tetromino` where it spawns on the grid, following the game's wrap and
+    /// gravity.
+    fn spawn(&self, tetromino: &Tetromino) -> Tetromino {
+        let mut spawned = self.generator.spawn(
+            self.grid,
+            tetromino.shape.clone(),
+            tetromino.current_rotation,
+        );
+        spawned.horizontal_wrap = self.horizontal_wrap;
+        spawned.gravity = self.gravity_dir;
+        spawned.move_to_entry();
+        spawned
+    }
+
+    pub fn horizontal_wrap(&self) -> bool {
+        self.horizontal_wrap
+    }
+
+    /// Lets tetrominos leave one side of the playground and come back on the other.
+    /// Ignored under sideways gravity, where pieces would fall around forever.
+    pub fn set_horizontal_wrap(&mut self, wrap: bool) {
+        let wrap = wrap && !self.gravity_sideways();
+        self.horizontal_wrap = wrap;
+        self.tetromino.horizontal_wrap = wrap;
+        self.next_tetromino.horizontal_wrap = wrap;
+    }
+
+    pub fn gravity_dir(&self) -> GravityDir {
+        self.gravity_dir
+    }
+
+    /// Makes tetrominos fall towards `gravity_dir`, entering from the opposite edge.
+    /// Sideways gravity turns horizontal wrap off. The falling and next tetrominos
+    /// are put back where they spawn.
+    pub fn set_gravity_dir(&mut self, gravity_dir: GravityDir) {
+        self.gravity_dir = gravity_dir;
+        if self.gravity_sideways() {
+            self.horizontal_wrap = false;
+        }
+        self.respawn();
+    }
+
+    fn gravity_sideways(&self) -> bool {
+        matches!(self.gravity_dir, GravityDir::Left | GravityDir::Right)
+    }
+
+    /// Whether a hard drop would leave the tetromino where it is, i.e. a ghost piece
+    /// would sit exactly under it.
+    pub fn ghost_overlaps_active(&self) -> bool {
+        self.tetromino.is_grounded()
+    }
+
+    /// Ticks from now until the falling tetromino locks if left alone. Pieces lock on
+    /// the first gravity step that cannot move them, since there is no lock delay.
+    pub fn predicted_lock_tick(&self) -> Option<u64> {
+        if self.over {
+            return None;
+        }
+        let mut dropped = self.tetromino.clone();
+        let mut cells = 0;
+        while dropped.move_down().is_ok() {
+            cells += 1;
+        }
+        let animation =
+            (self.clear_delay + self.spawn_anim_frames.saturating_sub(self.piece_age)) as u64;
+        let first_step = (GRAVITY_TICKS - self.counter) as u64;
+        Some(animation + first_step + GRAVITY_TICKS as u64 * cells)
+    }
+
+    /// Ticks since the falling tetromino spawned.
+    pub fn active_piece_age(&self) -> u32 {
+        self.piece_age
+    }
+
+    /// Whether the game is holding still after a clear.
+    pub fn in_clear_delay(&self) -> bool {
+        self.clear_delay > 0
+    }
+
+    /// Whether the falling tetromino is still in its spawn animation, so gravity does
+    /// not move it yet.
+    pub fn in_spawn_animation(&self) -> bool {
+        self.piece_age < self.spawn_anim_frames
+    }
+
+    /// Whether the next tetromino would fit where it spawns on the current grid.
+    pub fn next_spawn_safe(&self) -> bool {
+        self.spawn(&self.next_tetromino).fits()
+    }
+
+    pub fn position_is_valid(&self, shape: Shape, rotation: Rotation, topleft: Coord) -> bool {
+        let mut tetromino = Tetromino::with_rotation(self.grid, shape, rotation);
+        tetromino.horizontal_wrap = self.horizontal_wrap;
+        tetromino.topleft = topleft;
+        tetromino.fits()
+    }
+
+    /// Everything needed to reproduce a bug report, one field per line.
+    pub fn debug_dump(&self) -> String {
+        let mut dump = String::new();
+        let describe = |tetromino: &Tetromino| {
+            format!(
+                "{:?} rotation {} at y {} x {}",
+                tetromino.shape,
+                tetromino.current_rotation,
+                tetromino.topleft.y,
+                tetromino.topleft.x
+            )
+        };
+        writeln!(dump, "score: {}", self.score).unwrap();
+        writeln!(dump, "paused: {}", self.paused).unwrap();
+        writeln!(dump, "over: {}", self.over).unwrap();
+        writeln!(dump, "counter: {}", self.counter).unwrap();
+        writeln!(dump, "piece age: {}", self.piece_age).unwrap();
+        writeln!(dump, "last hole delta: {}", self.hole_delta).unwrap();
+        writeln!(dump, "randomizer: {:?}", self.generator.randomizer).unwrap();
+        writeln!(dump, "seed: {:?}", self.generator.seed).unwrap();
+        writeln!(dump, "pieces dealt: {}", self.piece_history().len()).unwrap();
+        writeln!(dump, "visible next: {}", self.visible_next).unwrap();
+        writeln!(dump, "soft drop: {:?}", self.soft_drop).unwrap();
+        writeln!(dump, "tetromino: {}", describe(&self.tetromino)).unwrap();
+        writeln!(dump, "next: {}", describe(&self.next_tetromino)).unwrap();
+        writeln!(dump, "grid:").unwrap();
+        for (landed, framed) in self.grid.iter().zip(self.frame().iter()) {
+            let line = landed
+                .iter()
+                .zip(framed.iter())
+                .map(|(landed, framed)| match (landed.value, framed.value) {
+                    (0, 0) => '.',
+                    (0, _) => '@',
+                    _ => '#',
+                })
+                .collect::<String>();
+            writeln!(dump, "{}", line).unwrap();
+        }
+        dump
+    }
+
+    /// The frame drawn with half blocks, two grid rows per line of text.
+    pub fn render_unicode(&self) -> String {
+        let mut rendered = String::new();
+        for rows in self.frame().chunks(2) {
+            for column in 0..PLAYGROUND_WIDTH as usize {
+                let top = rows[0][column].value != 0;
+                let bottom = rows.get(1).is_some_and(|row| row[column].value != 0);
+                rendered.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// The playground as one character per cell. The ghost marks where a hard drop
+    /// would land; the falling tetromino is drawn over it.
+    pub fn render_ascii(&self, charset: &RenderCharset) -> String {
+        let active = self.tetromino.cells();
+        let mut dropped = self.tetromino.clone();
+        dropped.move_all_the_way_down();
+        let ghost = dropped.cells();
+
+        let mut rendered = String::new();
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, block) in row.iter().enumerate() {
+                let cell = Coord {
+                    y: y as i32,
+                    x: x as i32,
+                };
+                rendered.push(if active.contains(&cell) {
+                    charset.active
+                } else if ghost.contains(&cell) {
+                    charset.ghost
+                } else if block.value != 0 && !self.invisible {
+                    charset.landed
+                } else {
+                    charset.empty
+                });
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Where the falling tetromino can land by rotating in place, sliding
+    /// sideways and dropping.
+    pub fn reachable_placements(&self) -> Vec<(Rotation, Coord)> {
+        self.tetromino.placements()
+    }
+
+    /// Placements reached by at most `max_inputs` moves, rotations or soft drops followed
+    /// by a hard drop, searched breadth first.
+    pub fn placements_within(&self, max_inputs: usize) -> Vec<(Rotation, Coord)> {
+        let mut placements = Vec::new();
+        let mut seen = vec![(self.tetromino.current_rotation, self.tetromino.topleft)];
+        let mut queue = VecDeque::new();
+        queue.push_back((self.tetromino.clone(), 0));
+        while let Some((tetromino, inputs)) = queue.pop_front() {
+            let mut dropped = tetromino.clone();
+            dropped.move_all_the_way_down();
+            if !placements.contains(&(dropped.current_rotation, dropped.topleft)) {
+                placements.push((dropped.current_rotation, dropped.topleft));
+            }
+            if inputs == max_inputs {
+                continue;
+            }
+            for step in 0..5 {
+                let mut next = tetromino.clone();
+                let moved = match step {
+                    0 => next.move_sideways(Direction::Left),
+                    1 => next.move_sideways(Direction::Right),
+                    2 => next.rotate(Direction::Left),
+                    3 => next.rotate(Direction::Right),
+                    _ => next.move_down(),
+                };
+                let state = (next.current_rotation, next.topleft);
+                if moved.is_ok() && !seen.contains(&state) {
+                    seen.push(state);
+                    queue.push_back((next, inputs + 1));
+                }
+            }
+        }
+        placements
+    }
+
+    /// Whether the falling tetromino can land somewhere without covering a new hole.
+    pub fn has_clean_placement(&self) -> bool {
+        self.reachable_placements()
+            .into_iter()
+            .any(|(rotation, topleft)| {
+                let mut game = self.clone();
+                game.tetromino.current_rotation = rotation;
+                game.tetromino.topleft = topleft;
+                game.land_tetromino().is_ok() && game.hole_delta <= 0
+            })
+    }
+
+    /// Leftmost columns at which hard dropping the falling tetromino, in its current
+    /// rotation, covers no new hole.
+    pub fn safe_columns(&self) -> Vec<i32> {
+        let mut columns = self
+            .reachable_placements()
+            .into_iter()
+            .filter(|(rotation, _)| *rotation == self.tetromino.current_rotation)
+            .filter_map(|(rotation, topleft)| {
+                let mut game = self.clone();
+                game.tetromino.current_rotation = rotation;
+                game.tetromino.topleft = topleft;
+                let leftmost = game.tetromino.cells().iter().map(|cell| cell.x).min()?;
+                match (game.land_tetromino(), game.hole_delta) {
+                    (Ok(()), delta) if delta <= 0 => Some(leftmost),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<i32>>();
+        columns.sort_unstable();
+        columns
+    }
+
+    /// Whether at most `depth` pieces can empty the board: the falling and next
+    /// tetrominos first, then any standard shape, since later pieces are not known yet.
+    /// Each height the missing cells allow is tried, lowest first, and no piece may
+    /// reach above it. After `PC_SEARCH_BUDGET` placements the answer is `false`, and
+    /// likewise for any gravity but `GravityDir::Down`, which the search does not model.
+    pub fn pc_reachable(&self, depth: usize) -> bool {
+        if self.over || self.gravity_dir != GravityDir::Down {
+            return false;
+        }
+        let known = [self.tetromino.clone(), self.next_tetromino.clone()];
+        let filled = self.grid.iter().map(Game::fill_count).sum::<u32>() as usize;
+        let mut budget = PC_SEARCH_BUDGET;
+        let mut failed = HashSet::new();
+        (self.max_height() as usize..=PLAYGROUND_HEIGHT as usize).any(|height| {
+            let missing = height * PLAYGROUND_WIDTH as usize - filled;
+            height > 0
+                && missing.is_multiple_of(4)
+                && missing / 4 <= depth
+                && self.pc_search(
+                    &self.grid,
+                    &known,
+                    missing / 4,
+                    height,
+                    &mut failed,
+                    &mut budget,
+                )
+        })
+    }
+
+    // Places exactly `pieces` more pieces within the bottom `height` rows. Boards that
+    // failed with only unknown pieces left are remembered in `failed`; the key's piece
+    // count also fixes the height.
+    fn pc_search(
+        &self,
+        grid: &Grid,
+        known: &[Tetromino],
+        pieces: usize,
+        height: usize,
+        failed: &mut HashSet<([u16; PLAYGROUND_HEIGHT as usize], usize)>,
+        budget: &mut usize,
+    ) -> bool {
+        let mut occupancy = [0; PLAYGROUND_HEIGHT as usize];
+        for (bits, row) in occupancy.iter_mut().zip(grid.iter()) {
+            for (column, block) in row.iter().enumerate() {
+                if block.value != 0 {
+                    *bits |= 1 << column;
+                }
+            }
+        }
+        if known.is_empty() && failed.contains(&(occupancy, pieces)) {
+            return false;
+        }
+
+        let candidates = match known.first() {
+            Some(tetromino) => vec![tetromino.clone()],
+            None => Shape::STANDARD
+                .iter()
+                .map(|shape| {
+                    let rotation = shape.get_possible_rotations()[0];
+                    self.generator.spawn(*grid, shape.clone(), rotation)
+                })
+                .collect(),
+        };
+        let top = PLAYGROUND_HEIGHT - height as i32;
+        for mut tetromino in candidates {
+            tetromino.grid = *grid;
+            tetromino.horizontal_wrap = self.horizontal_wrap;
+            for (rotation, topleft) in tetromino.placements() {
+                let mut placed = tetromino.clone();
+                placed.current_rotation = rotation;
+                placed.topleft = topleft;
+                if topleft.y <= 0 || placed.cells().iter().any(|cell| cell.y < top) {
+                    continue;
+                }
+                if *budget == 0 {
+                    return false;
+                }
+                *budget -= 1;
+                let mut after = *grid;
+                Game::place_tetromino(&mut after, &placed);
+                let mut cleared = 0;
+                loop {
+                    let full = after
+                        .iter()
+                        .filter(|row| Game::is_full(row, self.clear_threshold))
+                        .count();
+                    if full == 0 {
+                        break;
+                    }
+                    cleared += full;
+                    after = collapse(&after, self.clear_gravity, self.clear_threshold);
+                }
+                if after == Game::create_grid() {
+                    return true;
+                }
+                let rest = known.get(1..).unwrap_or(&[]);
+                if pieces > 1
+                    && cleared < height
+                    && self.pc_search(&after, rest, pieces - 1, height - cleared, failed, budget)
+                {
+                    return true;
+                }
+            }
+        }
+        if known.is_empty() {
+            failed.insert((occupancy, pieces));
+        }
+        false
+    }
+
+    fn lock(&mut self) -> TickOutcome {
+        if self.land_tetromino().is_err() {
+            match self.restart_point.take() {
+                Some(restart_point) => {
+                    *self = (*restart_point).clone();
+                    self.restart_point = Some(restart_point);
+                }
+                None => self.over = true,
+            }
+            return TickOutcome::GameOver;
+        }
+        let cleared = self.clear_rows();
+        self.tetromino = self.spawn(&self.next_tetromino);
+        let next = self.generator.next_tetromino(self.grid);
+        self.next_tetromino = self.spawn(&next);
+        self.piece_age = 0;
+        if !cleared.is_empty() {
+            self.clear_delay = self.clear_delay_frames;
+            TickOutcome::LinesCleared(cleared)
+        } else {
+            TickOutcome::Locked
+        }
+    }
+
+    /// The upcoming tetrominos the player is allowed to see.
+    pub fn next_preview(&self) -> Vec<&Tetromino> {
+        std::iter::once(&self.next_tetromino)
+            .take(self.visible_next)
+            .collect()
+    }
+
+    /// How many pieces away `shape` is among the falling tetromino (0) and the visible
+    /// preview, or `None` if it is not known yet.
+    pub fn pieces_until(&self, shape: Shape) -> Option<usize> {
+        std::iter::once(&self.tetromino)
+            .chain(self.next_preview())
+            .position(|tetromino| tetromino.shape == shape)
+    }
+
+    /// Refills the next queue from `provider` instead of the randomizer. The piece
+    /// already in the queue is kept. Clones of the game deal the same shapes from it.
+    pub fn set_piece_provider(&mut self, provider: Box<dyn FnMut() -> Shape>) {
+        self.generator.set_provider(provider);
+    }
+
+    /// Deals `shape` alongside the standard ones, once per bag with the `Bag`
+    /// randomizer. Shapes without rotations, or whose rotations are empty or differ in
+    /// size, are rejected.
+    pub fn register_shape(&mut self, shape: Shape) -> Result<(), String> {
+        self.generator.register(shape)
+    }
+
+    pub fn piece_history(&self) -> &[Shape] {
+        self.generator.dealt()
+    }
+
+    pub fn count_holes(&self) -> u32 {
+        let mut holes = 0;
+        for column in 0..PLAYGROUND_WIDTH as usize {
+            let mut covered = false;
+            for row in self.grid.iter() {
+                if row[column].value != 0 {
+                    covered = true;
+                } else if covered {
+                    holes += 1;
+                }
+            }
+        }
+        holes
+    }
+
+    /// Landed blocks with an empty cell directly beneath them.
+    pub fn overhang_cells(&self) -> u32 {
+        self.grid
+            .windows(2)
+            .map(|rows| {
+                rows[0]
+                    .iter()
+                    .zip(rows[1].iter())
+                    .filter(|(above, below)| above.value != 0 && below.value == 0)
+                    .count() as u32
+            })
+            .sum()
+    }
+
+    /// Landed cells in row-major order: top to bottom, then left to right. Renderers may
+    /// rely on this order.
+    pub fn occupied_cells(&self) -> Vec<Coord> {
+        let mut cells = Vec::new();
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, block) in row.iter().enumerate() {
+                if block.value != 0 {
+                    cells.push(Coord {
+                        y: y as i32,
+                        x: x as i32,
+                    });
+                }
+            }
+        }
+        cells
+    }
+
+    pub fn column_heights(&self) -> [u32; PLAYGROUND_WIDTH as usize] {
+        let mut heights = [0; PLAYGROUND_WIDTH as usize];
+        for (column, height) in heights.iter_mut().enumerate() {
+            if let Some(row) = self.grid.iter().position(|row| row[column].value != 0) {
+                *height = (PLAYGROUND_HEIGHT as usize - row) as u32;
+            }
+        }
+        heights
+    }
+
+    /// Column heights as one character each: 0-9, then a, b, ... for taller columns.
+    pub fn profile_string(&self) -> String {
+        self.column_heights()
+            .iter()
+            .map(|height| std::char::from_digit(*height, 36).unwrap())
+            .collect()
+    }
+
+    pub fn max_height(&self) -> u32 {
+        self.column_heights().iter().copied().max().unwrap()
+    }
+
+    pub fn bumpiness(&self) -> u32 {
+        self.column_heights()
+            .windows(2)
+            .map(|pair| (pair[0] as i32 - pair[1] as i32).unsigned_abs())
+            .sum()
+    }
+
+    /// Different colors among the landed blocks.
+    pub fn distinct_colors(&self) -> usize {
+        let mut colors = Vec::new();
+        for block in self.grid.iter().flatten() {
+            if let (true, Some(color)) = (block.value != 0, block.color) {
+                if !colors.contains(&color) {
+                    colors.push(color);
+                }
+            }
+        }
+        colors.len()
+    }
+
+    /// How messy the board is, from 0 (empty) to 1. Holes and max height weigh 0.4 each
+    /// and bumpiness 0.2. Each term saturates: holes at 12, height at the top of the
+    /// playground and bumpiness at twice the playground width.
+    pub fn difficulty_score(&self) -> f32 {
+        let holes = (self.count_holes() as f32 / 12.0).min(1.0);
+        let height = self.max_height() as f32 / PLAYGROUND_HEIGHT as f32;
+        let bumpiness = (self.bumpiness() as f32 / (2 * PLAYGROUND_WIDTH) as f32).min(1.0);
+        0.4 * holes + 0.4 * height + 0.2 * bumpiness
+    }
+
+    /// Filled/empty changes along each row, counting the walls as filled.
+    pub fn row_transitions(&self) -> u32 {
+        let mut transitions = 0;
+        for row in self.grid.iter() {
+            let mut previous_filled = true;
+            for block in row.iter() {
+                if (block.value != 0) != previous_filled {
+                    transitions += 1;
+                }
+                previous_filled = block.value != 0;
+            }
+            if !previous_filled {
+                transitions += 1;
+            }
+        }
+        transitions
+    }
+
+    /// Filled/empty changes down each column, counting the floor as filled.
+    pub fn column_transitions(&self) -> u32 {
+        let mut transitions = 0;
+        for column in 0..PLAYGROUND_WIDTH as usize {
+            let mut previous_filled = false;
+            for row in self.grid.iter() {
+                if (row[column].value != 0) != previous_filled {
+                    transitions += 1;
+                }
+                previous_filled = row[column].value != 0;
+            }
+            if !previous_filled {
+                transitions += 1;
+            }
+        }
+        transitions
+    }
+
+    /// All of the board metrics above, computed in a single pass over the grid.
+    pub fn feature_vector(&self) -> Features {
+        let mut features = Features {
+            holes: 0,
+            column_heights: [0; PLAYGROUND_WIDTH as usize],
+            max_height: 0,
+            bumpiness: 0,
+            row_transitions: 0,
+            column_transitions: 0,
+        };
+        let mut above_filled = [false; PLAYGROUND_WIDTH as usize];
+
+        for (rowidx, row) in self.grid.iter().enumerate() {
+            let mut previous_filled = true;
+            for (colidx, block) in row.iter().enumerate() {
+                let filled = block.value != 0;
+                if filled != previous_filled {
+                    features.row_transitions += 1;
+                }
+                if filled != above_filled[colidx] {
+                    features.column_transitions += 1;
+                }
+                if filled && features.column_heights[colidx] == 0 {
+                    features.column_heights[colidx] = (PLAYGROUND_HEIGHT as usize - rowidx) as u32;
+                }
+                if !filled && features.column_heights[colidx] != 0 {
+                    features.holes += 1;
+                }
+                previous_filled = filled;
+                above_filled[colidx] = filled;
+            }
+            if !previous_filled {
+                features.row_transitions += 1;
+            }
+        }
+
+        features.column_transitions +=
+            above_filled.iter().filter(|filled| !**filled).count() as u32;
+        features.max_height = features.column_heights.iter().copied().max().unwrap();
+        features.bumpiness = features
+            .column_heights
+            .windows(2)
+            .map(|pair| (pair[0] as i32 - pair[1] as i32).unsigned_abs())
+            .sum();
+        features
+    }
+
+    /// Holes created (positive) or uncovered (negative) by the last landed tetromino.
+    pub fn last_hole_delta(&self) -> i32 {
+        self.hole_delta
+    }
+
+    fn land_tetromino(&mut self) -> Result<(), &'static str> {
+        if self.tetromino.at_entry() {
+            return Err("Game over.");
+        }
+        if !self.tetromino.fits() {
+            return Err("Invalid position.");
+        }
+
+        let holes_before = self.count_holes() as i32;
+        Game::place_tetromino(&mut self.grid, &self.tetromino);
+        self.hole_delta = self.count_holes() as i32 - holes_before;
+        Ok(())
+    }
+
+    /// The landed grid with the falling tetromino drawn into it. In invisible mode
+    /// only the falling tetromino is drawn.
+    pub fn frame(&self) -> Grid {
+        let mut grid = if self.invisible {
+            Game::create_grid()
+        } else {
+            self.grid
+        };
+        Game::place_tetromino(&mut grid, &self.tetromino);
+        grid
+    }
+
+    fn place_tetromino(grid: &mut Grid, tetromino: &Tetromino) {
+        for Coord { y, x } in tetromino.cells() {
+            grid[y as usize][x as usize] = Block::new(1, Some(tetromino.color));
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+/// Lists the cells that differ between two grids, one per line, or `None` if they
+/// are equal. Easier to read in a failing test than two whole grids.
+pub fn grid_diff_report(a: &Grid, b: &Grid) -> Option<String> {
+    let mut report = String::new();
+    for (y, (row_a, row_b)) in a.iter().zip(b.iter()).enumerate() {
+        for (x, (block_a, block_b)) in row_a.iter().zip(row_b.iter()).enumerate() {
+            if block_a != block_b {
+                writeln!(report, "y {} x {}: {:?} != {:?}", y, x, block_a, block_b).unwrap();
+            }
+        }
+    }
+    if report.is_empty() {
+        None
+    } else {
+        Some(report)
+    }
+}
+
+/// Removes the full rows of `grid` and lets what is left fall according to `mode`.
+pub fn apply_gravity(grid: &Grid, mode: ClearGravity) -> Grid {
+    collapse(grid, mode, PLAYGROUND_WIDTH as u32)
+}
+
+fn collapse(grid: &Grid, mode: ClearGravity, threshold: u32) -> Grid {
+    match mode {
+        ClearGravity::Naive => {
+            let mut collapsed = Game::create_grid();
+            let remaining = grid.iter().filter(|row| !Game::is_full(row, threshold));
+            for (row, kept) in collapsed.iter_mut().rev().zip(remaining.rev()) {
+                *row = *kept;
+            }
+            collapsed
+        }
+        ClearGravity::Cascade => {
+            let mut collapsed = *grid;
+            for row in collapsed
+                .iter_mut()
+                .filter(|row| Game::is_full(row, threshold))
+            {
+                *row = Game::create_empty_row();
+            }
+            while settle_step(&mut collapsed) {}
+            collapsed
+        }
+    }
+}
+
+// Collects the filled cells connected to `start` through neighbours that `joins` accepts.
+fn flood(
+    grid: &Grid,
+    start: (usize, usize),
+    seen: &mut [[bool; PLAYGROUND_WIDTH as usize]; PLAYGROUND_HEIGHT as usize],
+    joins: impl Fn(&Block, &Block) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut group = vec![start];
+    seen[start.0][start.1] = true;
+    let mut next = 0;
+    while next < group.len() {
+        let (gy, gx) = group[next];
+        next += 1;
+        let neighbours = [
+            (gy.wrapping_sub(1), gx),
+            (gy + 1, gx),
+            (gy, gx.wrapping_sub(1)),
+            (gy, gx + 1),
+        ];
+        for &(ny, nx) in neighbours.iter() {
+            if ny < PLAYGROUND_HEIGHT as usize
+                && nx < PLAYGROUND_WIDTH as usize
+                && grid[ny][nx].value != 0
+                && !seen[ny][nx]
+                && joins(&grid[gy][gx], &grid[ny][nx])
+            {
+                seen[ny][nx] = true;
+                group.push((ny, nx));
+            }
+        }
+    }
+    group
+}
+
+// Moves every connected group of blocks that has room below it down by one row.
+fn settle_step(grid: &mut Grid) -> bool {
+    let mut moved = false;
+    let mut seen = [[false; PLAYGROUND_WIDTH as usize]; PLAYGROUND_HEIGHT as usize];
+    for y in (0..PLAYGROUND_HEIGHT as usize).rev() {
+        for x in 0..PLAYGROUND_WIDTH as usize {
+            if grid[y][x].value == 0 || seen[y][x] {
+                continue;
+            }
+            let mut group = flood(grid, (y, x), &mut seen, |_, _| true);
+            let can_fall = group.iter().all(|&(gy, gx)| {
+                gy + 1 < PLAYGROUND_HEIGHT as usize
+                    && (grid[gy + 1][gx].value == 0 || group.contains(&(gy + 1, gx)))
+            });
+            if can_fall {
+                group.sort_unstable_by_key(|&(gy, _)| std::cmp::Reverse(gy));
+                for (gy, gx) in group {
+                    grid[gy + 1][gx] = grid[gy][gx];
+                    grid[gy][gx] = Block::new(0, None);
+                }
+                moved = true;
+            }
+        }
+    }
+    moved
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Features {
+    pub holes: u32,
+    pub column_heights: [u32; PLAYGROUND_WIDTH as usize],
+    pub max_height: u32,
+    pub bumpiness: u32,
+    pub row_transitions: u32,
+    pub column_transitions: u32,
+}
+
+/// Score split by where it came from. The parts always add up to the score.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScoreBreakdown {
+    pub line_clears: u64,
+    /// Extra points for rows cleared by later steps of a cascade.
+    pub chains: u64,
+    pub color_groups: u64,
+    pub bombs: u64,
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> u64 {
+        self.line_clears + self.chains + self.color_groups + self.bombs
+    }
+}
+
+pub type Row = [Block; PLAYGROUND_WIDTH as usize];
+pub type Grid = [Row; PLAYGROUND_HEIGHT as usize];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Block {
+    pub value: u8,
+    pub color: Option<Color>,
+}
+
+impl Block {
+    pub fn new(value: u8, color: Option<Color>) -> Block {
+        Block { value, color }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TickOutcome {
+    Falling,
+    Locked,
+    /// Indices of the cleared rows, or columns under sideways gravity, as they were
+    /// before the stack collapsed. A cascade lists each step's rows in turn.
+    LinesCleared(Vec<usize>),
+    GameOver,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingConfig {
+    pub auto_restart: bool,
+    pub initial_board: Option<Grid>,
+}
+
+/// Characters used by `Game::render_ascii`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderCharset {
+    pub empty: char,
+    pub landed: char,
+    pub active: char,
+    pub ghost: char,
+}
+
+impl Default for RenderCharset {
+    fn default() -> RenderCharset {
+        RenderCharset {
+            empty: '.',
+            landed: '#',
+            active: '@',
+            ghost: '+',
+        }
+    }
+}
+
+/// Which way tetrominos fall. Sideways gravity clears full columns instead of rows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GravityDir {
+    Down,
+    Left,
+    Right,
+    Up,
+}
+
+/// What happens to the blocks above a cleared row.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClearGravity {
+    /// Everything above drops by the number of rows cleared beneath it.
+    Naive,
+    /// Each connected group of blocks falls on its own until it lands.
+    Cascade,
+}
+
+/// How far a single soft drop input moves the tetromino.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoftDropSpeed {
+    /// Up to this many cells. Zero turns soft drop off.
+    Cells(u32),
+    Instant,
+}
+
+/// What holding left and right at once does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SimultaneousInput {
+    /// Neither direction moves the tetromino.
+    Cancel,
+    FirstPressed,
+    LastPressed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Left,
+    Right,
+    SoftDrop,
+    RotateLeft,
+    RotateRight,
+    HardDrop,
+    ClearBoard,
+}
+
+#[derive(Clone, Copy)]
+pub enum Direction {
+    Left = -1,
+    Right = 1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coord {
+    pub y: i32,
+    pub x: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::CustomShape;
+
+    #[test]
+    fn rows_after_clear() {
+        let mut game = Game::new();
+        for column in 0..PLAYGROUND_WIDTH as usize {
+            game.grid[PLAYGROUND_HEIGHT as usize - 1][column] = Block::new(1, None);
+            game.grid[PLAYGROUND_HEIGHT as usize - 3][column] = Block::new(1, None);
+        }
+        game.grid[PLAYGROUND_HEIGHT as usize - 2][3] = Block::new(1, None);
+        game.grid[PLAYGROUND_HEIGHT as usize - 4][7] = Block::new(1, None);
+        let grid = game.grid;
+
+        let rows = game.rows_after_clear();
+        assert_eq!(game.grid, grid);
+        assert_eq!(rows.len(), PLAYGROUND_HEIGHT as usize);
+
+        assert_eq!(game.clear_rows().len(), 2);
+        assert_eq!(rows, game.grid.to_vec());
+        assert_eq!(rows[PLAYGROUND_HEIGHT as usize - 1][3], Block::new(1, None));
+        assert_eq!(rows[PLAYGROUND_HEIGHT as usize - 2][7], Block::new(1, None));
+    }
+
+    #[test]
+    fn rows_after_clear_cascade() {
+        let mut game = Game::new();
+        game.clear_gravity = ClearGravity::Cascade;
+        game.grid[PLAYGROUND_HEIGHT as usize - 1] =
+            [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        for block in game.grid[PLAYGROUND_HEIGHT as usize - 2][..9].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        game.grid[PLAYGROUND_HEIGHT as usize - 4][9] = Block::new(1, None);
+        let score = game.score();
+
+        let rows = game.rows_after_clear();
+        assert_eq!(game.score(), score);
+        assert_eq!(game.clear_rows().len(), 2);
+        assert_eq!(rows, game.grid.to_vec());
+        assert_eq!(game.grid, Game::create_grid());
+    }
+
+    #[test]
+    fn grid_diff_report() {
+        let a = Game::create_grid();
+        let mut b = a;
+        assert_eq!(super::grid_diff_report(&a, &b), None);
+
+        b[PLAYGROUND_HEIGHT as usize - 1][3] = Block::new(1, Some(Color::Red));
+        assert_eq!(
+            super::grid_diff_report(&a, &b),
+            Some(String::from(
+                "y 15 x 3: Block { value: 0, color: None } != Block { value: 1, color: Some(Red) }\n"
+            ))
+        );
+    }
+
+    #[test]
+    fn apply_gravity_naive() {
+        let mut grid = Game::create_grid();
+        grid[PLAYGROUND_HEIGHT as usize - 1] = [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        for block in grid[PLAYGROUND_HEIGHT as usize - 2][..5].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        grid[PLAYGROUND_HEIGHT as usize - 4][8] = Block::new(1, None);
+
+        let collapsed = apply_gravity(&grid, ClearGravity::Naive);
+
+        let mut expected = Game::create_grid();
+        for block in expected[PLAYGROUND_HEIGHT as usize - 1][..5].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        expected[PLAYGROUND_HEIGHT as usize - 3][8] = Block::new(1, None);
+        assert_eq!(collapsed, expected);
+    }
+
+    #[test]
+    fn apply_gravity_cascade() {
+        let mut grid = Game::create_grid();
+        grid[PLAYGROUND_HEIGHT as usize - 1] = [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        for block in grid[PLAYGROUND_HEIGHT as usize - 2][..5].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        grid[PLAYGROUND_HEIGHT as usize - 3][0] = Block::new(1, None);
+        grid[PLAYGROUND_HEIGHT as usize - 4][8] = Block::new(1, None);
+        grid[PLAYGROUND_HEIGHT as usize - 5][8] = Block::new(1, None);
+
+        let collapsed = apply_gravity(&grid, ClearGravity::Cascade);
+
+        let mut expected = Game::create_grid();
+        for block in expected[PLAYGROUND_HEIGHT as usize - 1][..5].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        expected[PLAYGROUND_HEIGHT as usize - 2][0] = Block::new(1, None);
+        expected[PLAYGROUND_HEIGHT as usize - 1][8] = Block::new(1, None);
+        expected[PLAYGROUND_HEIGHT as usize - 2][8] = Block::new(1, None);
+        assert_eq!(collapsed, expected);
+        assert_eq!(apply_gravity(&collapsed, ClearGravity::Cascade), collapsed);
+    }
+
+    #[test]
+    fn row_fill_counts() {
+        let mut game = Game::new();
+        for block in game.grid[PLAYGROUND_HEIGHT as usize - 1][..8].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        for block in game.grid[PLAYGROUND_HEIGHT as usize - 2][..7].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        let counts = game.row_fill_counts();
+        assert_eq!(counts[PLAYGROUND_HEIGHT as usize - 1], 8);
+        assert_eq!(counts[PLAYGROUND_HEIGHT as usize - 2], 7);
+        assert_eq!(
+            counts[..PLAYGROUND_HEIGHT as usize - 2].iter().sum::<u32>(),
+            0
+        );
+
+        assert!(game.clear_rows().is_empty());
+        game.clear_threshold = 8;
+        assert_eq!(game.full_rows(), vec![PLAYGROUND_HEIGHT as usize - 1]);
+        assert_eq!(game.clear_rows().len(), 1);
+        assert_eq!(game.row_fill_counts()[PLAYGROUND_HEIGHT as usize - 1], 7);
+    }
+
+    #[test]
+    fn cascade_chain() {
+        let mut game = Game::new();
+        game.clear_gravity = ClearGravity::Cascade;
+        for block in game.grid[PLAYGROUND_HEIGHT as usize - 1][..9].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        game.grid[PLAYGROUND_HEIGHT as usize - 2] =
+            [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.grid[PLAYGROUND_HEIGHT as usize - 3][9] = Block::new(1, None);
+        game.grid[PLAYGROUND_HEIGHT as usize - 4][0] = Block::new(1, None);
+
+        let bottom = PLAYGROUND_HEIGHT as usize - 1;
+        assert_eq!(game.clear_rows(), vec![bottom - 1, bottom]);
+        assert_eq!(game.last_chain(), 2);
+        assert_eq!(game.score(), POINTS_PER_ROW * 3);
+
+        let mut expected = Game::create_grid();
+        expected[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, None);
+        assert_eq!(game.grid, expected);
+
+        assert!(game.clear_rows().is_empty());
+        assert_eq!(game.last_chain(), 0);
+    }
+
+    #[test]
+    fn cascade_lines_cleared() {
+        let mut game = Game::new();
+        game.clear_gravity = ClearGravity::Cascade;
+        let bottom = PLAYGROUND_HEIGHT as usize - 1;
+        for block in game.grid[bottom][..9].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        for block in game.grid[bottom - 1][..6].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        game.grid[bottom - 3][9] = Block::new(1, None);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        game.tetromino.topleft = Coord {
+            y: bottom as i32 - 3,
+            x: 6,
+        };
+
+        assert_eq!(
+            game.lock_now(),
+            Ok(TickOutcome::LinesCleared(vec![bottom - 1, bottom]))
+        );
+        assert_eq!(game.grid, Game::create_grid());
+    }
+
+    #[test]
+    fn score_breakdown() {
+        let mut game = Game::new();
+        game.clear_gravity = ClearGravity::Cascade;
+        let bottom = PLAYGROUND_HEIGHT as usize - 1;
+        for block in game.grid[bottom][..9].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        game.grid[bottom - 1] = [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.grid[bottom - 2][9] = Block::new(1, None);
+        game.clear_rows();
+        game.grid[bottom][..3].copy_from_slice(&[Block::new(1, Some(Color::Red)); 3]);
+        game.clear_color_groups(3);
+        game.grid[bottom][5] = Block::new(1, None);
+        game.run_inputs(&[Input::HardDrop]).unwrap();
+        game.clear_bottom_n(1);
+
+        let breakdown = game.score_breakdown();
+        assert_eq!(
+            breakdown,
+            ScoreBreakdown {
+                line_clears: 2 * POINTS_PER_ROW,
+                chains: POINTS_PER_ROW,
+                color_groups: 3,
+                bombs: POINTS_PER_ROW,
+            }
+        );
+        assert_eq!(breakdown.total(), game.score());
+
+        game.score_cap = Some(game.score() + 1);
+        game.clear_bottom_n(1);
+        assert_eq!(game.score_breakdown().bombs, POINTS_PER_ROW + 1);
+        assert_eq!(game.score_breakdown().total(), game.score());
+    }
+
+    #[test]
+    fn score_cap() {
+        let mut game = Game::new();
+        game.score_cap = Some(25);
+        assert_eq!(game.clear_bottom_n(2), 2 * POINTS_PER_ROW);
+        assert!(!game.score_cap_reached());
+        assert!(!game.take_score_cap_event());
+        assert_eq!(game.clear_bottom_n(1), 5);
+        assert!(game.score_cap_reached());
+        assert_eq!(game.clear_bottom_n(3), 0);
+        assert_eq!(game.score(), 25);
+
+        assert!(game.take_score_cap_event());
+        assert!(!game.take_score_cap_event());
+        assert_eq!(game.clear_bottom_n(1), 0);
+        assert!(!game.take_score_cap_event());
+    }
+
+    #[test]
+    fn clear_color_groups() {
+        let mut game = Game::new();
+        let bottom = PLAYGROUND_HEIGHT as usize - 1;
+        for block in game.grid[bottom][..4].iter_mut() {
+            *block = Block::new(1, Some(Color::Red));
+        }
+        game.grid[bottom - 1][3] = Block::new(1, Some(Color::Red));
+        game.grid[bottom][6] = Block::new(1, Some(Color::Blue));
+        game.grid[bottom][7] = Block::new(1, Some(Color::Blue));
+        game.grid[bottom - 1][0] = Block::new(1, Some(Color::Green));
+        game.grid[bottom - 2][3] = Block::new(1, Some(Color::Green));
+
+        assert_eq!(game.clear_color_groups(5), 5);
+        assert_eq!(game.score(), 5);
+
+        let mut expected = Game::create_grid();
+        expected[bottom][0] = Block::new(1, Some(Color::Green));
+        expected[bottom][3] = Block::new(1, Some(Color::Green));
+        expected[bottom][6] = Block::new(1, Some(Color::Blue));
+        expected[bottom][7] = Block::new(1, Some(Color::Blue));
+        assert_eq!(super::grid_diff_report(&game.grid, &expected), None);
+
+        assert_eq!(game.clear_color_groups(5), 0);
+    }
+
+    #[test]
+    fn clear_bottom_n() {
+        let mut game = Game::new();
+        for row in PLAYGROUND_HEIGHT as usize - 5..PLAYGROUND_HEIGHT as usize {
+            game.grid[row][row % 3] = Block::new(1, None);
+        }
+        let grid = game.grid;
+
+        assert_eq!(game.clear_bottom_n(3), 3 * POINTS_PER_ROW);
+
+        assert_eq!(game.score(), 3 * POINTS_PER_ROW);
+        assert_eq!(&game.grid[3..], &grid[..PLAYGROUND_HEIGHT as usize - 3]);
+        assert_eq!(&game.grid[..3], &Game::create_grid()[..3]);
+        assert_eq!(game.tetromino.grid, game.grid);
+    }
+
+    #[test]
+    fn is_paused() {
+        let mut game = Game::new();
+        assert!(!game.is_paused());
+        game.toggle_pause();
+        assert!(game.is_paused());
+        game.toggle_pause();
+        assert!(!game.is_paused());
+    }
+
+    #[test]
+    fn apply() {
+        let mut game = Game::new();
+        let Coord { y, x } = game.tetromino.topleft;
+        assert_eq!(game.apply(Input::Left), Ok(()));
+        assert_eq!(game.apply(Input::SoftDrop), Ok(()));
+        assert_eq!(game.tetromino.topleft.x, x - 1);
+        assert_eq!(game.tetromino.topleft.y, y + 1);
+    }
+
+    #[test]
+    fn horizontal_input() {
+        let mut game = Game::new();
+        let x = game.tetromino.topleft.x;
+        let held = [Input::Right, Input::SoftDrop, Input::Left];
+        assert_eq!(game.horizontal_input(&[]), None);
+        assert_eq!(game.horizontal_input(&[Input::Left]), Some(Input::Left));
+        assert_eq!(
+            game.horizontal_input(&[Input::Right, Input::Right]),
+            Some(Input::Right)
+        );
+
+        let cases = [
+            (SimultaneousInput::Cancel, x),
+            (SimultaneousInput::FirstPressed, x + 1),
+            (SimultaneousInput::LastPressed, x - 1),
+        ];
+        for &(policy, expected) in cases.iter() {
+            game.simultaneous_input = policy;
+            let moved = match game.horizontal_input(&held) {
+                Some(input) => game.peek_apply(input),
+                None => game.clone(),
+            };
+            assert_eq!(moved.tetromino.topleft.x, expected);
+        }
+    }
+
+    #[test]
+    fn peek_apply() {
+        let game = Game::new();
+        let topleft = game.tetromino.topleft;
+
+        let peeked = game.peek_apply(Input::Left);
+        assert_eq!(peeked.tetromino.topleft.x, topleft.x - 1);
+        assert_eq!(peeked.tetromino.topleft.y, topleft.y);
+        assert_eq!(game.tetromino.topleft, topleft);
+    }
+
+    #[test]
+    fn next_preview() {
+        let mut game = Game::new();
+        assert_eq!(game.next_preview().len(), 1);
+        assert_eq!(game.next_preview()[0].shape, game.next_tetromino.shape);
+
+        game.visible_next = 0;
+        assert!(game.next_preview().is_empty());
+
+        let next = game.next_tetromino.shape.clone();
+        game.tetromino.shape = Shape::O;
+        game.tetromino.current_rotation = 51;
+        game.tetromino.move_all_the_way_down();
+        for _ in 0..5 {
+            game.handle_falling();
+        }
+        assert_eq!(game.tetromino.shape, next);
+        assert!(game.next_preview().is_empty());
+    }
+
+    #[test]
+    fn invisible() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.invisible = true;
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.tetromino.topleft.x = 0;
+        for row in game.grid.iter_mut() {
+            row[4] = Block::new(1, Some(Color::Red));
+        }
+        game.tetromino.grid = game.grid;
+
+        let frame = game.frame();
+        assert!(frame.iter().all(|row| row[4].value == 0));
+        assert_eq!(
+            frame
+                .iter()
+                .flatten()
+                .filter(|block| block.value != 0)
+                .count(),
+            4
+        );
+        assert_eq!(game.render_unicode().matches('█').count(), 2);
+        assert_eq!(game.render_unicode().matches('▀').count(), 0);
+        assert_eq!(game.apply(Input::Right), Err("Collision."));
+
+        game.invisible = false;
+        assert!(game.frame().iter().all(|row| row[4].value != 0));
+    }
+
+    #[test]
+    fn frame() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        let frame = game.frame();
+        let Coord { y, x } = game.tetromino.topleft;
+        for (row, column) in [(2, 2), (2, 3), (3, 2), (3, 3)].iter() {
+            assert_eq!(
+                frame[(y + row) as usize][(x + column) as usize],
+                Block::new(1, Some(Color::Blue))
+            );
+        }
+        assert_eq!(game.grid, Game::create_grid());
+    }
+
+    #[test]
+    fn horizontal_wrap() {
+        let mut game = Game::new();
+        game.set_horizontal_wrap(true);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        game.tetromino.horizontal_wrap = true;
+        game.tetromino.topleft.x = PLAYGROUND_WIDTH - 2;
+        let next = game.next_tetromino.shape.clone();
+
+        game.tetromino.move_all_the_way_down();
+        assert_eq!(game.lock_now(), Ok(TickOutcome::Locked));
+
+        let bottom = game.grid[PLAYGROUND_HEIGHT as usize - 1]
+            .iter()
+            .map(|block| block.value)
+            .collect::<Vec<u8>>();
+        assert_eq!(bottom, vec![1, 1, 0, 0, 0, 0, 0, 0, 1, 1]);
+        assert_eq!(game.tetromino.shape, next);
+        assert!(game.tetromino.horizontal_wrap);
+        assert!(game.next_tetromino.horizontal_wrap);
+    }
+
+    #[test]
+    fn active_piece_age() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.tetromino.topleft.y = PLAYGROUND_HEIGHT - 5;
+        assert_eq!(game.active_piece_age(), 0);
+
+        for age in 1..10 {
+            assert_eq!(game.tick(None), Ok(TickOutcome::Falling));
+            assert_eq!(game.active_piece_age(), age);
+        }
+        assert_eq!(game.tick(None), Ok(TickOutcome::Locked));
+        assert_eq!(game.active_piece_age(), 0);
+        assert_eq!(game.tick(None), Ok(TickOutcome::Falling));
+        assert_eq!(game.active_piece_age(), 1);
+    }
+
+    #[test]
+    fn debug_dump() {
+        let mut game = Game::with_seed(Randomizer::Tgm { rerolls: 4 }, 42);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, None);
+        game.handle_falling();
+
+        let dump = game.debug_dump();
+        for line in [
+            "score: 0",
+            "counter: 1",
+            "randomizer: Tgm { rerolls: 4 }",
+            "seed: Some(42)",
+            "tetromino: O rotation 51 at y 0 x 2",
+            "grid:",
+            "....@@....",
+            "#.........",
+        ]
+        .iter()
+        {
+            assert!(dump.lines().any(|dumped| dumped == *line), "{}", line);
+        }
+        assert_eq!(
+            dump.lines().skip_while(|line| *line != "grid:").count(),
+            PLAYGROUND_HEIGHT as usize + 1
+        );
+    }
+
+    #[test]
+    fn render_ascii() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, None);
+        game.tetromino.grid = game.grid;
+
+        let default = game.render_ascii(&RenderCharset::default());
+        let lines = default.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), PLAYGROUND_HEIGHT as usize);
+        assert_eq!(lines[2], "....@@....");
+
+        let charset = RenderCharset {
+            empty: ' ',
+            landed: 'X',
+            active: 'O',
+            ghost: 'o',
+        };
+        let rendered = game.render_ascii(&charset);
+        let mut expected = String::new();
+        for y in 0..PLAYGROUND_HEIGHT {
+            expected.push_str(match y {
+                2 | 3 => "    OO    ",
+                14 => "    oo    ",
+                15 => "X   oo    ",
+                _ => "          ",
+            });
+            expected.push('\n');
+        }
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn render_unicode() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::T, 114);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1] =
+            [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.grid[PLAYGROUND_HEIGHT as usize - 2][0] = Block::new(1, None);
+        game.grid[PLAYGROUND_HEIGHT as usize - 3][9] = Block::new(1, None);
+
+        let rendered = game.render_unicode();
+        let lines = rendered.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), PLAYGROUND_HEIGHT as usize / 2);
+        assert_eq!(lines[0], "          ");
+        assert_eq!(lines[1], "   ▀█▀    ");
+        assert_eq!(lines[6], "         ▄");
+        assert_eq!(lines[7], "█▄▄▄▄▄▄▄▄▄");
+    }
+
+    #[test]
+    fn reachable_placements() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        let placements = game.reachable_placements();
+        assert_eq!(placements.len(), PLAYGROUND_WIDTH as usize - 1);
+        assert!(placements.contains(&(51, Coord { y: 12, x: -2 })));
+        assert!(placements.contains(&(51, Coord { y: 12, x: 6 })));
+
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        let placements = game.reachable_placements();
+        assert_eq!(
+            placements.len(),
+            PLAYGROUND_WIDTH as usize + PLAYGROUND_WIDTH as usize - 3
+        );
+    }
+
+    #[test]
+    fn pc_reachable() {
+        let mut game = Game::new();
+        for column in 4..PLAYGROUND_WIDTH as usize {
+            game.grid[PLAYGROUND_HEIGHT as usize - 1][column] = Block::new(1, None);
+        }
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 8738);
+        assert!(game.pc_reachable(10));
+        assert!(!game.pc_reachable(0));
+
+        let mut upside_down = game.clone();
+        upside_down.set_gravity_dir(GravityDir::Up);
+        assert!(!upside_down.pc_reachable(10));
+
+        for column in [1, 2, 3, 5].iter() {
+            game.grid[PLAYGROUND_HEIGHT as usize - 1][*column] =
+                Block::new(*column as u8 % 2, None);
+        }
+        game.tetromino.grid = game.grid;
+        assert!(!game.pc_reachable(2));
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][3] = Block::new(0, None);
+        game.tetromino.grid = game.grid;
+        assert!(!game.pc_reachable(10));
+
+        // Two known Os and one more O of the unknown pieces fill the 2x6 gap.
+        let mut game = Game::new();
+        for row in game.grid[PLAYGROUND_HEIGHT as usize - 2..].iter_mut() {
+            for block in row[..4].iter_mut() {
+                *block = Block::new(1, None);
+            }
+        }
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.next_tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        assert!(!game.pc_reachable(2));
+        assert!(game.pc_reachable(3));
+
+        game.next_tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        assert!(game.pc_reachable(3));
+        game.next_tetromino = Tetromino::with_rotation(game.grid, Shape::S, 54);
+        assert!(!game.pc_reachable(3));
+    }
+
+    #[test]
+    fn has_clean_placement() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        assert!(game.has_clean_placement());
+
+        for column in (1..PLAYGROUND_WIDTH as usize).step_by(2) {
+            game.grid[PLAYGROUND_HEIGHT as usize - 1][column] = Block::new(1, None);
+        }
+        game.tetromino.grid = game.grid;
+        assert!(!game.has_clean_placement());
+    }
+
+    #[test]
+    fn corrupted_topleft_ends_game() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino.topleft = Coord { y: 40, x: -30 };
+        for _ in 0..5 {
+            let _ = game.tick(Some(Input::Left));
+        }
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn distinct_colors() {
+        let mut game = Game::new();
+        assert_eq!(game.distinct_colors(), 0);
+
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, Some(Color::Red));
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][1] = Block::new(1, Some(Color::Red));
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][2] = Block::new(1, Some(Color::Blue));
+        game.grid[PLAYGROUND_HEIGHT as usize - 2][0] = Block::new(1, Some(Color::Green));
+        game.grid[PLAYGROUND_HEIGHT as usize - 2][5] = Block::new(0, Some(Color::Cyan));
+        game.grid[PLAYGROUND_HEIGHT as usize - 3][0] = Block::new(1, None);
+        assert_eq!(game.distinct_colors(), 3);
+    }
+
+    #[test]
+    fn profile_string() {
+        let mut game = Game::new();
+        assert_eq!(game.profile_string(), "0000000000");
+
+        for column in 0..PLAYGROUND_WIDTH as usize {
+            for row in game.grid[PLAYGROUND_HEIGHT as usize - column..].iter_mut() {
+                row[column] = Block::new(1, None);
+            }
+        }
+        assert_eq!(game.profile_string(), "0123456789");
+
+        for row in game.grid[PLAYGROUND_HEIGHT as usize - 12..].iter_mut() {
+            row[3] = Block::new(1, None);
+        }
+        game.grid[0][9] = Block::new(1, None);
+        assert_eq!(game.profile_string(), "012c45678g");
+    }
+
+    #[test]
+    fn placements_within() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::T, 114);
+
+        let mut dropped = game.tetromino.clone();
+        dropped.move_all_the_way_down();
+        assert_eq!(
+            game.placements_within(0),
+            vec![(dropped.current_rotation, dropped.topleft)]
+        );
+
+        let mut previous = game.placements_within(0);
+        for budget in 1..8 {
+            let placements = game.placements_within(budget);
+            assert!(previous
+                .iter()
+                .all(|placement| placements.contains(placement)));
+            assert!(placements.len() >= previous.len());
+            previous = placements;
+        }
+        assert!(previous.len() > 1);
+        for placement in game.reachable_placements() {
+            assert!(game.placements_within(16).contains(&placement));
+        }
+    }
+
+    #[test]
+    fn overhang_cells() {
+        let mut game = Game::new();
+        let bottom = PLAYGROUND_HEIGHT as usize - 1;
+        game.grid[bottom][0] = Block::new(1, None);
+        for block in game.grid[bottom - 1][..4].iter_mut() {
+            *block = Block::new(1, None);
+        }
+        game.grid[bottom - 3][6] = Block::new(1, None);
+        game.grid[bottom - 4][6] = Block::new(1, None);
+        assert_eq!(game.overhang_cells(), 4);
+        assert_eq!(game.count_holes(), 3 + 3);
+    }
+
+    #[test]
+    fn occupied_cells() {
+        let mut game = Game::new();
+        assert!(game.occupied_cells().is_empty());
+
+        for &(y, x) in &[(15, 0), (3, 7), (15, 9), (3, 2), (9, 5)] {
+            game.grid[y][x] = Block::new(1, None);
+        }
+        assert_eq!(
+            game.occupied_cells(),
+            vec![
+                Coord { y: 3, x: 2 },
+                Coord { y: 3, x: 7 },
+                Coord { y: 9, x: 5 },
+                Coord { y: 15, x: 0 },
+                Coord { y: 15, x: 9 },
+            ]
+        );
+    }
+
+    #[test]
+    fn difficulty_score() {
+        let mut game = Game::new();
+        assert!(game.difficulty_score() < 0.05);
+
+        for (rowidx, row) in game.grid.iter_mut().enumerate().skip(1) {
+            for (colidx, block) in row.iter_mut().enumerate() {
+                if (rowidx + colidx) % 2 == 0 {
+                    *block = Block::new(1, None);
+                }
+            }
+        }
+        assert!(game.difficulty_score() > 0.8);
+        assert!(game.difficulty_score() <= 1.0);
+    }
+
+    #[test]
+    fn training_restart() {
+        let mut game = Game::with_seed(Randomizer::Bag, 9);
+        let mut board = Game::create_grid();
+        board[PLAYGROUND_HEIGHT as usize - 1][..7].copy_from_slice(&[Block::new(1, None); 7]);
+        game.set_training(TrainingConfig {
+            auto_restart: true,
+            initial_board: Some(board),
+        });
+        let shapes = (
+            game.tetromino.shape.clone(),
+            game.next_tetromino.shape.clone(),
+        );
+        let fork = game.clone();
+        assert!(Rc::ptr_eq(
+            fork.restart_point.as_ref().unwrap(),
+            game.restart_point.as_ref().unwrap()
+        ));
+
+        game.tetromino.move_all_the_way_down();
+        game.lock_now().unwrap();
+        game.grid[1] = [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.tetromino.grid = game.grid;
+        assert_eq!(game.lock_now(), Ok(TickOutcome::GameOver));
+
+        assert!(!game.is_over());
+        assert_eq!(game.grid, board);
+        assert_eq!(game.tetromino.grid, board);
+        assert_eq!(
+            (
+                game.tetromino.shape.clone(),
+                game.next_tetromino.shape.clone()
+            ),
+            shapes
+        );
+        assert!(game
+            .debug_dump()
+            .lines()
+            .any(|line| line == "seed: Some(9)"));
+
+        let cells = game.tetromino.cells();
+        for cell in cells.iter() {
+            let below = Coord {
+                y: cell.y + 1,
+                x: cell.x,
+            };
+            if !cells.contains(&below) {
+                game.grid[below.y as usize][below.x as usize] = Block::new(1, None);
+            }
         }
+        game.tetromino.grid = game.grid;
+        assert_eq!(game.apply(Input::HardDrop), Ok(()));
+        assert!(!game.is_over());
+        assert_eq!(game.grid, board);
+
+        game.set_training(TrainingConfig {
+            auto_restart: false,
+            initial_board: None,
+        });
+        assert_eq!(game.lock_now(), Ok(TickOutcome::GameOver));
+        assert!(game.is_over());
     }
 
-    pub fn create_grid() -> Grid {
-        [Game::create_empty_row(); PLAYGROUND_HEIGHT as usize]
+    #[test]
+    fn safe_columns() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        assert_eq!(
+            game.safe_columns(),
+            (0..PLAYGROUND_WIDTH - 1).collect::<Vec<i32>>()
+        );
+
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, None);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][5] = Block::new(1, None);
+        game.tetromino.grid = game.grid;
+        assert_eq!(game.safe_columns(), vec![1, 2, 3, 6, 7, 8]);
     }
 
-    fn create_empty_row() -> [Block; PLAYGROUND_WIDTH as usize] {
-        [Block::new(0, None); PLAYGROUND_WIDTH as usize]
+    #[test]
+    fn simulate_tick_is_deterministic() {
+        let inputs = [
+            Input::Left,
+            Input::RotateRight,
+            Input::Right,
+            Input::HardDrop,
+        ];
+        let run = || {
+            let mut game = Game::with_seed(Randomizer::Bag, 13);
+            let mut outcomes = Vec::new();
+            for step in 0..400 {
+                if step % 7 == 0 {
+                    let _ = game.apply(inputs[step / 7 % inputs.len()]);
+                }
+                outcomes.push(game.simulate_tick());
+            }
+            (game.debug_dump(), outcomes)
+        };
+        let first = run();
+        assert!(first.1.contains(&TickOutcome::Locked));
+        for _ in 0..10 {
+            assert_eq!(run(), first);
+        }
+    }
+
+    #[test]
+    fn ghost_overlaps_active() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        assert!(!game.ghost_overlaps_active());
+
+        game.tetromino.move_all_the_way_down();
+        assert!(game.ghost_overlaps_active());
+
+        game.lock_now().unwrap();
+        for row in game.grid[4..].iter_mut() {
+            *row = [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        }
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        assert!(game.ghost_overlaps_active());
+    }
+
+    #[test]
+    fn pieces_until() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::T, 114);
+        game.next_tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        assert_eq!(game.pieces_until(Shape::T), Some(0));
+        assert_eq!(game.pieces_until(Shape::I), Some(1));
+        assert_eq!(game.pieces_until(Shape::O), None);
+
+        game.visible_next = 0;
+        assert_eq!(game.pieces_until(Shape::I), None);
+    }
+
+    #[test]
+    fn legal_inputs() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::T, 114);
+        game.tetromino.topleft.y = 4;
+        assert_eq!(
+            game.legal_inputs(),
+            vec![
+                Input::Left,
+                Input::Right,
+                Input::SoftDrop,
+                Input::RotateLeft,
+                Input::RotateRight,
+                Input::HardDrop,
+            ]
+        );
+
+        game.grid = [[Block::new(1, None); PLAYGROUND_WIDTH as usize]; PLAYGROUND_HEIGHT as usize];
+        for Coord { y, x } in game.tetromino.cells() {
+            game.grid[y as usize][x as usize] = Block::new(0, None);
+        }
+        game.tetromino.grid = game.grid;
+        assert_eq!(game.legal_inputs(), vec![Input::HardDrop]);
+    }
+
+    #[test]
+    fn daily_seed() {
+        let seed = Game::daily_seed(2021, 3, 14);
+        assert_eq!(Game::daily_seed(2021, 3, 14), seed);
+        assert_ne!(Game::daily_seed(2021, 3, 15), seed);
+        assert_ne!(Game::daily_seed(2021, 4, 14), seed);
+        assert_ne!(Game::daily_seed(2022, 3, 14), seed);
+        assert_ne!(Game::daily_seed(2021, 1, 3), Game::daily_seed(2021, 3, 1));
+
+        let today = Game::with_seed(Randomizer::Bag, seed);
+        let again = Game::with_seed(Randomizer::Bag, seed);
+        assert_eq!(today.piece_history(), again.piece_history());
+    }
+
+    #[test]
+    fn restore_board() {
+        let mut game = Game::with_seed(Randomizer::Bag, 4);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, None);
+        game.tetromino.grid = game.grid;
+        let saved = game.save_board();
+
+        for _ in 0..3 {
+            game.run_inputs(&[Input::HardDrop]).unwrap();
+        }
+        assert_ne!(game.grid, saved);
+        let active = (game.tetromino.shape.clone(), game.tetromino.topleft);
+        let history = game.piece_history().to_vec();
+
+        game.restore_board(saved);
+        assert_eq!(game.grid, saved);
+        assert_eq!(game.tetromino.grid, saved);
+        assert_eq!(
+            (game.tetromino.shape.clone(), game.tetromino.topleft),
+            active
+        );
+        assert_eq!(game.piece_history(), &history[..]);
     }
 
-    pub fn clear_rows(&mut self) {
-        for i in 0..self.grid.len() {
-            if self.grid[i].iter().fold(0, |acc, x| acc + x.value) as i32 == PLAYGROUND_WIDTH {
-                let row = Game::create_empty_row();
-                self.grid[i] = row;
-                self.grid[..i + 1].rotate_right(1);
-                self.tetromino.grid = self.grid;
-                self.score += PLAYGROUND_WIDTH as u64;
+    #[test]
+    fn predicted_lock_tick() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        assert_eq!(game.predicted_lock_tick(), Some(5 + 5 * 12));
+
+        game.tick(None).unwrap();
+        game.tick(None).unwrap();
+        let predicted = game.predicted_lock_tick().unwrap();
+        assert_eq!(predicted, 63);
+        let mut ticks = 0;
+        loop {
+            ticks += 1;
+            if game.tick(None).unwrap() == TickOutcome::Locked {
+                break;
             }
         }
+        assert_eq!(ticks, predicted);
     }
 
-    pub fn handle_falling(&mut self) {
-        self.counter += 1;
-        if self.counter == 5 {
-            if self.tetromino.move_down().is_err() {
-                if self.land_tetromino().is_err() {
-                    curses_teardown();
-                    std::process::exit(0);
-                } else {
-                    self.tetromino = self.next_tetromino.clone();
-                    self.tetromino.grid = self.grid;
-                    self.next_tetromino = Tetromino::new(self.grid);
-                }
+    #[test]
+    fn spawn_animation() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.spawn_anim_frames = 8;
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        assert_eq!(game.predicted_lock_tick(), Some(8 + 5 + 5 * 12));
+
+        for _ in 0..8 {
+            assert!(game.in_spawn_animation());
+            game.tick(None).unwrap();
+            assert_eq!(game.tetromino.topleft.y, 0);
+        }
+        assert!(!game.in_spawn_animation());
+        for _ in 0..GRAVITY_TICKS {
+            game.tick(None).unwrap();
+        }
+        assert_eq!(game.tetromino.topleft.y, 1);
+
+        game.lock_now().unwrap();
+        assert!(game.in_spawn_animation());
+    }
+
+    #[test]
+    fn clear_delay() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.clear_delay_frames = 8;
+        let bottom = PLAYGROUND_HEIGHT as usize - 1;
+        for column in 0..PLAYGROUND_WIDTH as usize - 4 {
+            game.grid[bottom][column] = Block::new(1, None);
+        }
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        game.tetromino.topleft.x = PLAYGROUND_WIDTH - 4;
+        assert_eq!(
+            game.tick(Some(Input::HardDrop)),
+            Ok(TickOutcome::LinesCleared(vec![bottom]))
+        );
+
+        let topleft = game.tetromino.topleft;
+        for _ in 0..8 {
+            assert!(game.in_clear_delay());
+            game.counter = GRAVITY_TICKS - 1;
+            assert_eq!(game.tick(Some(Input::Left)), Ok(TickOutcome::Falling));
+            assert_eq!(game.tetromino.topleft, topleft);
+        }
+        assert!(!game.in_clear_delay());
+        game.tick(None).unwrap();
+        assert_eq!(game.tetromino.topleft.y, topleft.y + 1);
+    }
+
+    #[test]
+    fn hard_drop_locks() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.apply(Input::HardDrop).unwrap();
+        assert_eq!(
+            game.grid.iter().flatten().filter(|b| b.value != 0).count(),
+            4
+        );
+        assert_eq!(game.tetromino.topleft.y, 0);
+        assert_eq!(game.tick(Some(Input::HardDrop)), Ok(TickOutcome::Locked));
+
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.hard_drop_locks = false;
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.apply(Input::HardDrop).unwrap();
+        assert!(game.tetromino.is_grounded());
+        assert_eq!(game.grid, Game::create_grid());
+        let x = game.tetromino.topleft.x;
+        game.apply(Input::Left).unwrap();
+        assert_eq!(game.tetromino.topleft.x, x - 1);
+    }
+
+    #[test]
+    fn gravity_left() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.set_horizontal_wrap(true);
+        game.set_gravity_dir(GravityDir::Left);
+        assert!(!game.horizontal_wrap());
+        assert!(game.tetromino.at_entry());
+        let x = game.tetromino.topleft.x;
+        for _ in 0..GRAVITY_TICKS {
+            game.tick(None).unwrap();
+        }
+        assert_eq!(game.tetromino.topleft.x, x - 1);
+
+        for row in 0..PLAYGROUND_HEIGHT as usize {
+            if !(6..10).contains(&row) {
+                game.grid[row][0] = Block::new(1, None);
             }
-            self.counter = 0;
         }
+        game.grid[3][1] = Block::new(1, None);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 8738);
+        game.tetromino.gravity = GravityDir::Left;
+        game.tetromino.topleft = Coord { y: 6, x: 4 };
+        game.tetromino.move_all_the_way_down();
+        assert!(game.tetromino.cells().iter().all(|cell| cell.x == 0));
+
+        assert_eq!(game.lock_now(), Ok(TickOutcome::LinesCleared(vec![0])));
+        assert_eq!(game.score(), POINTS_PER_ROW);
+        assert_eq!(game.grid[3][0], Block::new(1, None));
+        assert_eq!(game.grid[3][1], Block::new(0, None));
+        assert!(game.tetromino.at_entry());
+        assert_eq!(game.tetromino.gravity, GravityDir::Left);
+
+        assert_eq!(game.lock_now(), Ok(TickOutcome::GameOver));
     }
 
-    fn land_tetromino(&mut self) -> Result<(), &'static str> {
-        if self.tetromino.topleft.y <= 0 {
-            return Err("Game over.");
+    #[test]
+    fn gravity_up() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.set_gravity_dir(GravityDir::Up);
+        for column in 0..PLAYGROUND_WIDTH as usize {
+            if !(3..7).contains(&column) {
+                game.grid[0][column] = Block::new(1, None);
+            }
         }
+        game.grid[1][0] = Block::new(1, None);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        game.tetromino.gravity = GravityDir::Up;
+        game.tetromino.move_to_entry();
+        game.tetromino.move_all_the_way_down();
 
-        let current_rotation = self.tetromino.current_rotation;
-        let tetrovec = self.tetromino.shape.to_4x4(current_rotation);
+        assert_eq!(game.lock_now(), Ok(TickOutcome::LinesCleared(vec![0])));
+        assert_eq!(game.grid[0][0], Block::new(1, None));
+        assert_eq!(
+            game.grid.iter().flatten().filter(|b| b.value != 0).count(),
+            1
+        );
+    }
 
-        for (rowidx, row) in tetrovec.into_iter().enumerate() {
-            for (colidx, column) in row.into_iter().enumerate() {
-                if column != 0 {
-                    let Coord { y, x } = self.tetromino.topleft;
-                    self.grid[rowidx + y as usize][(colidx as i32 + x as i32) as usize] = Block {
-                        value: column as u8,
-                        color: Some(self.tetromino.color),
-                    }
-                }
+    #[test]
+    fn hard_drop_after_gravity_lock() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        let bottom = PLAYGROUND_HEIGHT as usize - 1;
+        for column in 0..PLAYGROUND_WIDTH as usize - 1 {
+            game.grid[bottom][column] = Block::new(1, None);
+        }
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 8738);
+        game.tetromino.topleft.x = PLAYGROUND_WIDTH - 3;
+        game.tetromino.move_all_the_way_down();
+        game.counter = GRAVITY_TICKS - 1;
+
+        assert_eq!(
+            game.tick(Some(Input::HardDrop)),
+            Ok(TickOutcome::LinesCleared(vec![bottom]))
+        );
+        assert_eq!(
+            game.grid.iter().flatten().filter(|b| b.value != 0).count(),
+            3
+        );
+    }
+
+    #[test]
+    fn hard_drop_top_out() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.grid[4] = [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+
+        assert_eq!(game.apply(Input::HardDrop), Err("Game over."));
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn piece_provider() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        let cycle = [Shape::L, Shape::J];
+        let mut index = 0;
+        game.set_piece_provider(Box::new(move || {
+            index += 1;
+            cycle[index % cycle.len()].clone()
+        }));
+        let queued = game.next_tetromino.shape.clone();
+
+        game.run_inputs(&[Input::HardDrop]).unwrap();
+        assert_eq!(game.tetromino.shape, queued);
+        assert_eq!(game.next_tetromino.shape, Shape::J);
+        game.run_inputs(&[Input::HardDrop]).unwrap();
+        assert_eq!(game.tetromino.shape, Shape::J);
+        assert_eq!(game.next_tetromino.shape, Shape::L);
+
+        let peeked = game.peek_apply(Input::HardDrop);
+        assert_eq!(peeked.next_tetromino.shape, Shape::J);
+        game.run_inputs(&[Input::HardDrop]).unwrap();
+        assert_eq!(game.next_tetromino.shape, Shape::J);
+    }
+
+    #[test]
+    fn run_inputs() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 8738);
+        game.run_inputs(&[Input::RotateRight, Input::Left, Input::HardDrop])
+            .unwrap();
+
+        let bottom = &game.grid[PLAYGROUND_HEIGHT as usize - 1];
+        let filled: Vec<usize> = (0..PLAYGROUND_WIDTH as usize)
+            .filter(|&x| bottom[x].value != 0)
+            .collect();
+        assert_eq!(filled, vec![0, 1, 2, 3]);
+        assert_eq!(
+            game.grid.iter().flatten().filter(|b| b.value != 0).count(),
+            4
+        );
+    }
+
+    #[test]
+    fn run_inputs_game_over() {
+        let mut game = Game::with_seed(Randomizer::Random, 1);
+        for row in game.grid[1..].iter_mut() {
+            row[PLAYGROUND_WIDTH as usize / 2] = Block::new(1, None);
+        }
+        game.tetromino.grid = game.grid;
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        game.tetromino.topleft = Coord { y: -2, x: 2 };
+        assert_eq!(
+            game.run_inputs(&[Input::HardDrop, Input::Left]),
+            Err("Game over.")
+        );
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn lock_now() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.tetromino.topleft = Coord { y: 5, x: 3 };
+        let next = game.next_tetromino.shape.clone();
+
+        assert_eq!(game.lock_now(), Ok(TickOutcome::Locked));
+
+        for (row, column) in [(7, 5), (7, 6), (8, 5), (8, 6)].iter() {
+            assert_eq!(game.grid[*row][*column], Block::new(1, Some(Color::Blue)));
+        }
+        assert_eq!(game.count_holes(), 2 * (PLAYGROUND_HEIGHT as u32 - 9));
+        assert_eq!(game.tetromino.shape, next);
+    }
+
+    #[test]
+    fn lock_now_lines_cleared() {
+        let mut game = Game::new();
+        for row in PLAYGROUND_HEIGHT as usize - 4..PLAYGROUND_HEIGHT as usize - 1 {
+            for column in 0..PLAYGROUND_WIDTH as usize - 1 {
+                game.grid[row][column] = Block::new(1, None);
             }
         }
-        Ok(())
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][PLAYGROUND_WIDTH as usize - 1] =
+            Block::new(1, None);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 8738);
+        game.tetromino.topleft.x = PLAYGROUND_WIDTH - 3;
+        game.tetromino.move_all_the_way_down();
+
+        let rows = vec![
+            PLAYGROUND_HEIGHT as usize - 4,
+            PLAYGROUND_HEIGHT as usize - 3,
+            PLAYGROUND_HEIGHT as usize - 2,
+        ];
+        assert_eq!(game.lock_now(), Ok(TickOutcome::LinesCleared(rows)));
+        assert_eq!(game.score(), 3 * POINTS_PER_ROW);
     }
-}
 
-pub type Grid = [[Block; PLAYGROUND_WIDTH as usize]; PLAYGROUND_HEIGHT as usize];
+    #[test]
+    fn lock_now_invalid_position() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.tetromino.topleft = Coord { y: 5, x: 3 };
+        game.grid[7][5] = Block::new(1, None);
+        game.tetromino.grid = game.grid;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Block {
-    pub value: u8,
-    pub color: Option<Color>,
-}
+        assert_eq!(game.lock_now(), Err("Invalid position."));
+    }
 
-impl Block {
-    pub fn new(value: u8, color: Option<Color>) -> Block {
-        Block { value, color }
+    #[test]
+    fn position_is_valid() {
+        let mut game = Game::new();
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, None);
+
+        assert!(game.position_is_valid(Shape::I, 240, Coord { y: 0, x: 0 }));
+        assert!(game.position_is_valid(Shape::I, 240, Coord { y: 12, x: 0 }));
+        assert!(!game.position_is_valid(
+            Shape::I,
+            240,
+            Coord {
+                y: 0,
+                x: PLAYGROUND_WIDTH - 3
+            }
+        ));
+        assert!(!game.position_is_valid(Shape::I, 240, Coord { y: 14, x: 0 }));
+        assert!(!game.position_is_valid(Shape::I, 240, Coord { y: 13, x: 0 }));
+
+        let across_seam = Coord {
+            y: 0,
+            x: PLAYGROUND_WIDTH - 3,
+        };
+        game.set_horizontal_wrap(true);
+        assert!(game.position_is_valid(Shape::I, 240, across_seam));
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::I, 240);
+        game.set_horizontal_wrap(true);
+        game.tetromino.topleft = across_seam;
+        assert!(game.tetromino.fits());
+        assert!(!game.position_is_valid(Shape::I, 240, Coord { y: 14, x: 0 }));
     }
-}
 
-#[derive(Clone, Copy)]
-pub enum Direction {
-    Left = -1,
-    Right = 1,
-}
-#[derive(Clone, Copy)]
-pub struct Coord {
-    pub y: i32,
-    pub x: i32,
-}
+    #[test]
+    fn flip_vertical_twice() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.tetromino.topleft.x = 4;
+        for column in 0..PLAYGROUND_WIDTH as usize {
+            for row in PLAYGROUND_HEIGHT as usize - 1 - column % 3..PLAYGROUND_HEIGHT as usize {
+                game.grid[row][column] = Block::new(1, Some(Color::Red));
+            }
+        }
+        let grid = game.grid;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        game.flip_vertical();
+        assert_eq!(game.grid[0], grid[PLAYGROUND_HEIGHT as usize - 1]);
+        assert_eq!(game.grid[2], grid[PLAYGROUND_HEIGHT as usize - 3]);
+        game.flip_vertical();
+
+        assert_eq!(game.grid, grid);
+        assert!(!game.is_over());
+    }
+
+    #[test]
+    fn flip_vertical_lifts_tetromino() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.tetromino.topleft.y = 2;
+        game.grid[0] = [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.grid[PLAYGROUND_HEIGHT as usize - 5] =
+            [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+
+        game.flip_vertical();
+        assert_eq!(game.tetromino.topleft.y, 0);
+        assert!(game.tetromino.fits());
+        assert!(!game.is_over());
+
+        game.grid[PLAYGROUND_HEIGHT as usize - 3] =
+            [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.flip_vertical();
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn spawn_offset() {
+        let mut game = Game::with_seed(Randomizer::Random, 3);
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.next_tetromino = Tetromino::with_rotation(game.grid, Shape::T, 114);
+        game.set_spawn_offset(2);
+        assert_eq!(game.spawn_offset(), 2);
+        assert_eq!(game.tetromino.topleft.x, 2 + 2);
+        assert_eq!(game.next_preview()[0].topleft.x, 2 + 2);
+
+        game.tetromino.move_all_the_way_down();
+        game.lock_now().unwrap();
+        assert_eq!(game.tetromino.shape, Shape::T);
+        assert_eq!(game.tetromino.topleft.x, 2 + 2);
+
+        game.set_spawn_offset(-30);
+        assert_eq!(
+            game.tetromino.cells().iter().map(|cell| cell.x).min(),
+            Some(0)
+        );
+        game.next_tetromino = Tetromino::with_rotation(game.grid, Shape::T, 114);
+        game.set_spawn_offset(30);
+        assert!(game.tetromino.fits());
+        for row in 0..3 {
+            game.grid[row][8] = Block::new(1, None);
+        }
+        assert!(game.next_spawn_safe());
+        assert!(game.next_tetromino.fits());
+        game.next_tetromino.grid = game.grid;
+        assert!(!game.next_tetromino.fits());
+    }
+
+    #[test]
+    fn next_spawn_safe() {
+        let mut game = Game::new();
+        assert!(game.next_spawn_safe());
+
+        for row in 2..PLAYGROUND_HEIGHT as usize {
+            for column in 1..PLAYGROUND_WIDTH as usize {
+                game.grid[row][column] = Block::new(1, None);
+            }
+        }
+        let next = game.next_tetromino.shape.clone();
+        assert!(!game.next_spawn_safe());
+        assert_eq!(game.next_tetromino.shape, next);
+    }
+
+    #[test]
+    fn clear_board() {
+        let mut game = Game::new();
+        game.grid[PLAYGROUND_HEIGHT as usize - 1] =
+            [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.clear_rows();
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, None);
+        let topleft = game.tetromino.topleft;
+
+        assert_eq!(game.apply(Input::ClearBoard), Ok(()));
+
+        assert_eq!(game.grid, Game::create_grid());
+        assert_eq!(game.tetromino.grid, Game::create_grid());
+        assert_eq!(game.score(), PLAYGROUND_WIDTH as u64);
+        assert_eq!(game.tetromino.topleft.x, topleft.x);
+        assert_eq!(game.tetromino.topleft.y, topleft.y);
+    }
+
+    #[test]
+    fn soft_drop_cells() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.soft_drop = SoftDropSpeed::Cells(5);
+
+        assert_eq!(game.tick(Some(Input::SoftDrop)), Ok(TickOutcome::Falling));
+        assert_eq!(game.tetromino.topleft.y, 5);
+        assert_eq!(game.tick(Some(Input::SoftDrop)), Ok(TickOutcome::Falling));
+        assert_eq!(game.tetromino.topleft.y, 10);
+        assert_eq!(game.tick(Some(Input::SoftDrop)), Ok(TickOutcome::Falling));
+        assert_eq!(game.tetromino.topleft.y, PLAYGROUND_HEIGHT - 4);
+    }
+
+    #[test]
+    fn soft_drop_zero_cells() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.soft_drop = SoftDropSpeed::Cells(0);
+
+        assert_eq!(game.apply(Input::SoftDrop), Ok(()));
+        assert_eq!(game.tetromino.topleft.y, 0);
+    }
+
+    #[test]
+    fn soft_drop_instant() {
+        let mut game = Game::new();
+        game.tetromino = Tetromino::with_rotation(game.grid, Shape::O, 51);
+        game.soft_drop = SoftDropSpeed::Instant;
+
+        assert_eq!(game.tick(Some(Input::SoftDrop)), Ok(TickOutcome::Falling));
+        assert_eq!(game.tetromino.topleft.y, PLAYGROUND_HEIGHT - 4);
+        assert_eq!(game.grid, Game::create_grid());
+    }
+
+    #[test]
+    fn tick_game_over() {
+        let mut game = Game::new();
+        for column in 0..PLAYGROUND_WIDTH as usize {
+            if column != 9 {
+                for row in 0..PLAYGROUND_HEIGHT as usize {
+                    game.grid[row][column] = Block::new(1, None);
+                }
+            }
+        }
+        game.tetromino.grid = game.grid;
+        for _ in 0..4 {
+            assert_eq!(game.tick(None), Ok(TickOutcome::Falling));
+        }
+        assert_eq!(game.tick(None), Ok(TickOutcome::GameOver));
+        assert!(game.is_over());
+        assert_eq!(game.tick(None), Err("Game over."));
+    }
+
+    #[test]
+    fn score() {
+        let mut game = Game::new();
+        assert_eq!(game.score(), 0);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1] =
+            [Block::new(1, None); PLAYGROUND_WIDTH as usize];
+        game.clear_rows();
+        assert_eq!(game.score(), PLAYGROUND_WIDTH as u64);
+    }
+
+    #[test]
+    fn counter() {
+        let mut game = Game::new();
+        assert_eq!(game.counter(), 0);
+        game.handle_falling();
+        game.handle_falling();
+        assert_eq!(game.counter(), 2);
+    }
 
     #[test]
     fn create_grid() {
@@ -164,4 +2897,146 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn count_holes() {
+        let mut game = Game::new();
+        assert_eq!(game.count_holes(), 0);
+
+        game.grid[PLAYGROUND_HEIGHT as usize - 3][2] = Block::new(1, None);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][2] = Block::new(1, None);
+        game.grid[PLAYGROUND_HEIGHT as usize - 2][5] = Block::new(1, None);
+
+        assert_eq!(game.count_holes(), 2);
+    }
+
+    #[test]
+    fn column_heights() {
+        let mut game = Game::new();
+        game.grid[PLAYGROUND_HEIGHT as usize - 3][2] = Block::new(1, None);
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][5] = Block::new(1, None);
+
+        assert_eq!(game.column_heights(), [0, 0, 3, 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(game.max_height(), 3);
+        assert_eq!(game.bumpiness(), 8);
+    }
+
+    #[test]
+    fn transitions() {
+        let mut game = Game::new();
+        assert_eq!(game.row_transitions(), 2 * PLAYGROUND_HEIGHT as u32);
+        assert_eq!(game.column_transitions(), PLAYGROUND_WIDTH as u32);
+
+        game.grid[PLAYGROUND_HEIGHT as usize - 3][2] = Block::new(1, None);
+
+        assert_eq!(game.row_transitions(), 2 * PLAYGROUND_HEIGHT as u32 + 2);
+        assert_eq!(game.column_transitions(), PLAYGROUND_WIDTH as u32 + 2);
+    }
+
+    #[test]
+    fn feature_vector() {
+        let mut boards = vec![Game::create_grid()];
+
+        let mut staircase = Game::create_grid();
+        for (rowidx, row) in staircase.iter_mut().enumerate() {
+            for (colidx, block) in row.iter_mut().enumerate() {
+                if rowidx + colidx >= PLAYGROUND_HEIGHT as usize {
+                    *block = Block::new(1, None);
+                }
+            }
+        }
+        boards.push(staircase);
+
+        let mut holes = Game::create_grid();
+        for column in 0..PLAYGROUND_WIDTH as usize {
+            holes[(column * 7) % PLAYGROUND_HEIGHT as usize][column] = Block::new(1, None);
+            holes[PLAYGROUND_HEIGHT as usize - 1][(column * 3) % PLAYGROUND_WIDTH as usize] =
+                Block::new(1, None);
+        }
+        boards.push(holes);
+
+        let mut game = Game::new();
+        for grid in boards {
+            game.grid = grid;
+            assert_eq!(
+                game.feature_vector(),
+                Features {
+                    holes: game.count_holes(),
+                    column_heights: game.column_heights(),
+                    max_height: game.max_height(),
+                    bumpiness: game.bumpiness(),
+                    row_transitions: game.row_transitions(),
+                    column_transitions: game.column_transitions(),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn last_hole_delta_flat_placement() {
+        let mut game = Game::new();
+        game.tetromino.shape = Shape::O;
+        game.tetromino.current_rotation = 51;
+        game.tetromino.topleft = Coord {
+            y: PLAYGROUND_HEIGHT - 4,
+            x: 0,
+        };
+
+        assert_eq!(game.land_tetromino(), Ok(()));
+        assert_eq!(game.last_hole_delta(), 0);
+    }
+
+    #[test]
+    fn last_hole_delta_overhang_placement() {
+        let mut game = Game::new();
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][0] = Block::new(1, None);
+        game.tetromino.shape = Shape::I;
+        game.tetromino.current_rotation = 240;
+        game.tetromino.topleft = Coord {
+            y: PLAYGROUND_HEIGHT - 4,
+            x: 0,
+        };
+
+        assert_eq!(game.land_tetromino(), Ok(()));
+        assert_eq!(game.last_hole_delta(), 3);
+    }
+
+    #[test]
+    fn piece_history() {
+        let mut game = Game::with_seed(Randomizer::Random, 42);
+        game.tetromino.shape = Shape::O;
+        game.tetromino.current_rotation = 51;
+        game.tetromino.topleft.y = PLAYGROUND_HEIGHT - 4;
+        for _ in 0..5 {
+            game.handle_falling();
+        }
+
+        let mut generator = Generator::seeded(Randomizer::Random, 42);
+        let expected = (0..3)
+            .map(|_| generator.next_tetromino(Game::create_grid()).shape)
+            .collect::<Vec<Shape>>();
+        assert_eq!(game.piece_history(), &expected[..]);
+    }
+
+    #[test]
+    fn land_custom_shape() {
+        let dot = Shape::Custom(CustomShape {
+            name: String::from("dot"),
+            color: Color::Red,
+            rotations: vec![32],
+        });
+        let mut game = Game::new();
+        game.register_shape(dot.clone()).unwrap();
+        game.tetromino = Tetromino::with_rotation(game.grid, dot, 32);
+        game.tetromino.move_all_the_way_down();
+        let Coord { x, .. } = game.tetromino.topleft;
+        for _ in 0..5 {
+            game.handle_falling();
+        }
+
+        assert_eq!(
+            game.grid[PLAYGROUND_HEIGHT as usize - 1][x as usize + 2],
+            Block::new(1, Some(Color::Red))
+        );
+    }
 }
@@ -0,0 +1,11 @@
+#[cfg(test)]
+#[allow(clippy::single_component_path_imports)]
+use rstest_reuse;
+
+pub mod ai;
+pub mod core;
+pub mod randomizer;
+pub mod replay;
+pub mod shape;
+pub mod tetromino;
+pub mod ui;
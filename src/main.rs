@@ -1,13 +1,6 @@
-#[cfg(test)]
-use rstest_reuse;
-
-use crate::core::{Direction, Game};
 use ncurses as nc;
-
-mod core;
-mod shape;
-mod tetromino;
-mod ui;
+use tetris_rs::core::{Game, Input};
+use tetris_rs::ui;
 
 const KEY_A: i32 = b'a' as i32;
 const KEY_D: i32 = b'd' as i32;
@@ -15,6 +8,18 @@ const KEY_S: i32 = b's' as i32;
 const KEY_P: i32 = b'p' as i32;
 const ESC: i32 = 27;
 
+fn to_input(key: i32) -> Option<Input> {
+    match key {
+        nc::KEY_LEFT => Some(Input::Left),
+        nc::KEY_RIGHT => Some(Input::Right),
+        nc::KEY_DOWN => Some(Input::SoftDrop),
+        KEY_A => Some(Input::RotateLeft),
+        KEY_D | nc::KEY_UP => Some(Input::RotateRight),
+        KEY_S => Some(Input::HardDrop),
+        _ => None,
+    }
+}
+
 fn main() {
     ui::curses_init();
 
@@ -29,54 +34,32 @@ fn main() {
         ui::erase_screens(inner_screen, border_screen);
         nc::box_(border_screen, 0, 0);
 
-        ui::draw_landed_tetrominos(inner_screen, &game.grid);
+        if !game.invisible {
+            ui::draw_landed_tetrominos(inner_screen, &game.grid);
+        }
         ui::draw_tetromino(inner_screen, &game.tetromino);
-        ui::draw_next_tetromino(&game.next_tetromino);
-        ui::draw_score(game.score);
+        for next in game.next_preview() {
+            ui::draw_next_tetromino(next);
+        }
+        ui::draw_score(game.score());
 
         ui::refresh_screens(inner_screen);
 
         let user_input = nc::wgetch(inner_screen);
 
         if user_input == KEY_P {
-            game.paused = !game.paused;
+            game.toggle_pause();
         }
 
-        if !game.paused {
-            game.handle_falling();
-            game.clear_rows();
-            match user_input {
-                nc::KEY_LEFT => {
-                    if game.tetromino.move_sideways(Direction::Left).is_err() {
-                        continue;
-                    }
-                }
-                nc::KEY_RIGHT => {
-                    if game.tetromino.move_sideways(Direction::Right).is_err() {
-                        continue;
-                    }
-                }
-                nc::KEY_DOWN => {
-                    if game.tetromino.move_down().is_err() {
-                        continue;
-                    }
-                }
-                KEY_A => {
-                    if game.tetromino.rotate(Direction::Left).is_err() {
-                        continue;
-                    }
-                }
-                KEY_D | nc::KEY_UP => {
-                    if game.tetromino.rotate(Direction::Right).is_err() {
-                        continue;
-                    }
-                }
-                KEY_S => {
-                    game.tetromino.move_all_the_way_down();
-                }
-                ESC => break,
-                _ => {}
+        if !game.is_paused() {
+            if user_input == ESC {
+                break;
             }
+            let _ = game.tick(to_input(user_input));
+        }
+
+        if game.is_over() {
+            break;
         }
     }
     ui::curses_teardown();
@@ -0,0 +1,435 @@
+use crate::core::{Direction, Grid};
+use crate::shape::{Rotation, Shape};
+use crate::tetromino::Tetromino;
+
+use rand::{prelude::SliceRandom, rngs::StdRng, Rng, SeedableRng};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+const TGM_HISTORY_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Randomizer {
+    Random,
+    Bag,
+    Tgm {
+        rerolls: u8,
+    },
+    /// Beginner assist: shapes with more clean placements on the board are likelier.
+    /// Without a board it draws like `Random`.
+    Adaptive,
+}
+
+pub fn shuffle_bag(rng: &mut impl Rng) -> [Shape; 7] {
+    let mut bag = Shape::STANDARD;
+    bag.shuffle(rng);
+    bag
+}
+
+/// An outside source of shapes, such as a server, shared by a generator and its clones.
+/// Every shape it yields is kept, so each clone deals the same sequence no matter which
+/// of them asked for a shape first.
+struct PieceProvider {
+    source: Box<dyn FnMut() -> Shape>,
+    feed: Vec<Shape>,
+}
+
+impl PieceProvider {
+    fn shape_at(&mut self, index: usize) -> Shape {
+        while self.feed.len() <= index {
+            let shape = (self.source)();
+            self.feed.push(shape);
+        }
+        self.feed[index].clone()
+    }
+}
+
+#[derive(Clone)]
+pub struct Generator {
+    pub randomizer: Randomizer,
+    pub seed: Option<u64>,
+    history: VecDeque<Shape>,
+    bag: Vec<Shape>,
+    shapes: Vec<Shape>,
+    dealt: Vec<Shape>,
+    provider: Option<Rc<RefCell<PieceProvider>>>,
+    provided: usize,
+    spawn_offset: i32,
+    rng: StdRng,
+}
+
+impl Generator {
+    pub fn new(randomizer: Randomizer) -> Generator {
+        Generator::with_rng(randomizer, None, StdRng::from_entropy())
+    }
+
+    pub fn seeded(randomizer: Randomizer, seed: u64) -> Generator {
+        Generator::with_rng(randomizer, Some(seed), StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(randomizer: Randomizer, seed: Option<u64>, rng: StdRng) -> Generator {
+        // TGM starts with a history full of S and Z so the first pieces are
+        // unlikely to be the awkward ones.
+        let history = vec![Shape::Z, Shape::S, Shape::Z, Shape::S].into();
+        Generator {
+            randomizer,
+            seed,
+            history,
+            bag: Vec::new(),
+            shapes: Shape::STANDARD.to_vec(),
+            dealt: Vec::new(),
+            provider: None,
+            provided: 0,
+            spawn_offset: 0,
+            rng,
+        }
+    }
+
+    pub fn dealt(&self) -> &[Shape] {
+        &self.dealt
+    }
+
+    pub fn spawn_offset(&self) -> i32 {
+        self.spawn_offset
+    }
+
+    /// Columns to move each tetromino from its shape's spawn column.
+    pub fn set_spawn_offset(&mut self, offset: i32) {
+        self.spawn_offset = offset;
+    }
+
+    /// A tetromino at its spawn position on `grid`, moved by the spawn offset as far as
+    /// the walls and landed blocks allow.
+    pub fn spawn(&self, grid: Grid, shape: Shape, rotation: Rotation) -> Tetromino {
+        let mut tetromino = Tetromino::with_rotation(grid, shape, rotation);
+        let direction = if self.spawn_offset < 0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        };
+        for _ in 0..self.spawn_offset.unsigned_abs() {
+            if tetromino.move_sideways(direction).is_err() {
+                break;
+            }
+        }
+        tetromino
+    }
+
+    /// Deals shapes from `provider` instead of the randomizer from now on, in this
+    /// generator and every clone made from it afterwards. Look-ahead on a clone replays
+    /// the same shapes the original will get.
+    pub fn set_provider(&mut self, provider: Box<dyn FnMut() -> Shape>) {
+        self.provider = Some(Rc::new(RefCell::new(PieceProvider {
+            source: provider,
+            feed: Vec::new(),
+        })));
+        self.provided = 0;
+    }
+
+    /// Adds `shape` to the shapes drawn from, unless it could not be played.
+    pub fn register(&mut self, shape: Shape) -> Result<(), String> {
+        shape.validate_playable()?;
+        self.shapes.push(shape);
+        Ok(())
+    }
+
+    // The pool starts with the standard shapes and only grows, so it is never empty.
+    fn draw(&mut self) -> Shape {
+        self.shapes
+            .choose(&mut self.rng)
+            .cloned()
+            .unwrap_or_else(rand::random)
+    }
+
+    // Each shape weighs one more than its clean placements over all rotations.
+    fn draw_adaptive(&mut self, grid: &Grid) -> Shape {
+        let weights = self
+            .shapes
+            .iter()
+            .map(|shape| clean_placements(grid, shape) + 1)
+            .collect::<Vec<usize>>();
+        let indices = (0..self.shapes.len()).collect::<Vec<usize>>();
+        let index = *indices
+            .choose_weighted(&mut self.rng, |index| weights[*index])
+            .unwrap();
+        self.shapes[index].clone()
+    }
+
+    pub fn next_shape(&mut self) -> Shape {
+        self.next_shape_on(None)
+    }
+
+    fn next_shape_on(&mut self, grid: Option<&Grid>) -> Shape {
+        if let Some(provider) = self.provider.as_ref() {
+            let shape = provider.borrow_mut().shape_at(self.provided);
+            self.provided += 1;
+            self.dealt.push(shape.clone());
+            return shape;
+        }
+        let shape = match self.randomizer {
+            Randomizer::Random => self.draw(),
+            Randomizer::Adaptive => match grid {
+                Some(grid) => self.draw_adaptive(grid),
+                None => self.draw(),
+            },
+            // Each bag holds every shape in the pool once, registered ones included.
+            Randomizer::Bag => {
+                if self.bag.is_empty() {
+                    self.bag = self.shapes.clone();
+                    self.bag.shuffle(&mut self.rng);
+                    self.bag.reverse();
+                }
+                self.bag.pop().unwrap()
+            }
+            Randomizer::Tgm { rerolls } => {
+                let mut shape = self.draw();
+                for _ in 0..rerolls {
+                    if !self.history.contains(&shape) {
+                        break;
+                    }
+                    shape = self.draw();
+                }
+                if self.history.len() == TGM_HISTORY_SIZE {
+                    self.history.pop_front();
+                }
+                self.history.push_back(shape.clone());
+                shape
+            }
+        };
+        self.dealt.push(shape.clone());
+        shape
+    }
+
+    pub fn next_tetromino(&mut self, grid: Grid) -> Tetromino {
+        let shape = self.next_shape_on(Some(&grid));
+        let rotation = shape
+            .get_possible_rotations()
+            .choose(&mut self.rng)
+            .copied()
+            .unwrap();
+        self.spawn(grid, shape, rotation)
+    }
+}
+
+// Drops in each rotation that cover no hole, counted like `Game::safe_columns`.
+fn clean_placements(grid: &Grid, shape: &Shape) -> usize {
+    shape
+        .get_possible_rotations()
+        .into_iter()
+        .map(|rotation| {
+            let spawned = Tetromino::with_rotation(*grid, shape.clone(), rotation);
+            spawned
+                .placements()
+                .into_iter()
+                .filter(|&(placed, topleft)| {
+                    let mut landed = spawned.clone();
+                    landed.current_rotation = placed;
+                    landed.topleft = topleft;
+                    placed == rotation && topleft.y > 0 && !landed.covers_hole()
+                })
+                .count()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Block, Game, PLAYGROUND_HEIGHT};
+    use crate::shape::CustomShape;
+    use crate::ui::Color;
+
+    fn immediate_repeats(randomizer: Randomizer, draws: usize) -> usize {
+        let mut generator = Generator::new(randomizer);
+        let shapes = (0..draws)
+            .map(|_| generator.next_shape())
+            .collect::<Vec<Shape>>();
+        shapes.windows(2).filter(|pair| pair[0] == pair[1]).count()
+    }
+
+    #[test]
+    fn adaptive_avoids_s_and_z_on_flat_stack() {
+        let mut grid = Game::create_grid();
+        for row in grid[6..].iter_mut() {
+            for block in row[..9].iter_mut() {
+                *block = Block::new(1, None);
+            }
+        }
+        let mut generator = Generator::seeded(Randomizer::Adaptive, 3);
+        let draws = 350;
+        let s_and_z = (0..draws)
+            .map(|_| generator.next_tetromino(grid).shape)
+            .filter(|shape| *shape == Shape::S || *shape == Shape::Z)
+            .count();
+        assert!(s_and_z < draws / 7, "{}", s_and_z);
+    }
+
+    #[test]
+    fn clean_placements_match_safe_columns() {
+        let mut game = Game::with_seed(Randomizer::Random, 5);
+        for (column, height) in [3, 1, 0, 2, 2, 4, 1, 0, 3, 5].iter().enumerate() {
+            for row in PLAYGROUND_HEIGHT as usize - height..PLAYGROUND_HEIGHT as usize {
+                game.grid[row][column] = Block::new(1, None);
+            }
+        }
+        game.grid[PLAYGROUND_HEIGHT as usize - 1][5] = Block::new(0, None);
+        for shape in Shape::STANDARD.iter() {
+            let safe: usize = shape
+                .get_possible_rotations()
+                .into_iter()
+                .map(|rotation| {
+                    game.tetromino = Tetromino::with_rotation(game.grid, shape.clone(), rotation);
+                    game.safe_columns().len()
+                })
+                .sum();
+            assert_eq!(clean_placements(&game.grid, shape), safe, "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn provider() {
+        let mut generator = Generator::seeded(Randomizer::Bag, 1);
+        let cycle = [Shape::I, Shape::O, Shape::T];
+        let mut index = 0;
+        generator.set_provider(Box::new(move || {
+            index += 1;
+            cycle[(index - 1) % cycle.len()].clone()
+        }));
+        let mut forked = generator.clone();
+
+        let shapes = (0..4)
+            .map(|_| generator.next_shape())
+            .collect::<Vec<Shape>>();
+        assert_eq!(shapes, vec![Shape::I, Shape::O, Shape::T, Shape::I]);
+        assert_eq!(forked.next_shape(), Shape::I);
+        assert_eq!(generator.next_shape(), Shape::O);
+        assert_eq!(generator.dealt()[..4], shapes[..]);
+
+        let mut ahead = generator.clone();
+        assert_eq!(ahead.next_shape(), Shape::T);
+        assert_eq!(generator.next_shape(), Shape::T);
+    }
+
+    #[test]
+    fn spawn_offset() {
+        let mut generator = Generator::new(Randomizer::Random);
+        let grid = Game::create_grid();
+        generator.set_spawn_offset(-2);
+        assert_eq!(generator.spawn(grid, Shape::T, 114).topleft.x, 0);
+
+        generator.set_spawn_offset(40);
+        let cells = generator.spawn(grid, Shape::T, 114).cells();
+        assert_eq!(cells.iter().map(|cell| cell.x).max(), Some(9));
+
+        let mut blocked = grid;
+        blocked[2][7] = Block::new(1, None);
+        let spawned = generator.spawn(blocked, Shape::T, 114);
+        assert!(spawned.fits());
+        assert_eq!(spawned.cells().iter().map(|cell| cell.x).max(), Some(6));
+    }
+
+    #[test]
+    fn shuffle_bag_seeded() {
+        let mut rng = StdRng::seed_from_u64(42);
+        assert_eq!(
+            shuffle_bag(&mut rng),
+            [
+                Shape::T,
+                Shape::S,
+                Shape::L,
+                Shape::O,
+                Shape::J,
+                Shape::I,
+                Shape::Z
+            ]
+        );
+    }
+
+    #[test]
+    fn bag_deals_each_shape_once() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut generator = Generator::seeded(Randomizer::Bag, 42);
+        for _ in 0..3 {
+            let bag = (0..7)
+                .map(|_| generator.next_shape())
+                .collect::<Vec<Shape>>();
+            assert_eq!(bag, shuffle_bag(&mut rng).to_vec());
+        }
+    }
+
+    #[test]
+    fn tgm_history_size() {
+        let mut generator = Generator::new(Randomizer::Tgm { rerolls: 4 });
+        for _ in 0..10 {
+            generator.next_shape();
+            assert_eq!(generator.history.len(), TGM_HISTORY_SIZE);
+        }
+    }
+
+    #[test]
+    fn dealt() {
+        let mut generator = Generator::new(Randomizer::Random);
+        let shapes = (0..5)
+            .map(|_| generator.next_shape())
+            .collect::<Vec<Shape>>();
+        assert_eq!(generator.dealt(), &shapes[..]);
+    }
+
+    #[test]
+    fn register() {
+        let dot = Shape::Custom(CustomShape {
+            name: String::from("dot"),
+            color: Color::Red,
+            rotations: vec![32],
+        });
+        let mut generator = Generator::new(Randomizer::Random);
+        generator.register(dot.clone()).unwrap();
+        assert!((0..1000).any(|_| generator.next_shape() == dot));
+
+        let empty = Shape::Custom(CustomShape {
+            name: String::from("empty"),
+            color: Color::Red,
+            rotations: vec![0],
+        });
+        assert!(generator.register(empty.clone()).is_err());
+        assert!((0..1000).all(|_| generator.next_shape() != empty));
+    }
+
+    #[test]
+    fn register_bag() {
+        let dot = Shape::Custom(CustomShape {
+            name: String::from("dot"),
+            color: Color::Red,
+            rotations: vec![32],
+        });
+        let mut generator = Generator::seeded(Randomizer::Bag, 3);
+        generator.register(dot.clone()).unwrap();
+        for _ in 0..3 {
+            let mut bag = (0..8)
+                .map(|_| generator.next_shape())
+                .collect::<Vec<Shape>>();
+            assert_eq!(bag.iter().filter(|shape| **shape == dot).count(), 1);
+            bag.retain(|shape| *shape != dot);
+            for shape in Shape::STANDARD.iter() {
+                assert!(bag.contains(shape));
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_is_deterministic() {
+        let mut first = Generator::seeded(Randomizer::Tgm { rerolls: 4 }, 42);
+        let mut second = Generator::seeded(Randomizer::Tgm { rerolls: 4 }, 42);
+        for _ in 0..50 {
+            assert_eq!(first.next_shape(), second.next_shape());
+        }
+    }
+
+    #[test]
+    fn tgm_rarely_repeats() {
+        let random_repeats = immediate_repeats(Randomizer::Random, 10_000);
+        let tgm_repeats = immediate_repeats(Randomizer::Tgm { rerolls: 4 }, 10_000);
+        assert!(tgm_repeats * 4 < random_repeats);
+    }
+}
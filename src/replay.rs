@@ -0,0 +1,417 @@
+use crate::core::{Game, Grid, Input};
+use crate::randomizer::Randomizer;
+use std::cell::RefCell;
+use std::fmt;
+
+const KEYFRAME_INTERVAL: usize = 64;
+
+#[derive(Clone)]
+pub struct Replay {
+    pub randomizer: Randomizer,
+    pub seed: u64,
+    pub inputs: Vec<Option<Input>>,
+    pub checksum: u64,
+    keyframes: RefCell<Keyframes>,
+}
+
+// Games saved every KEYFRAME_INTERVAL inputs, valid for the randomizer, seed and inputs
+// they were played from.
+#[derive(Clone, Default)]
+struct Keyframes {
+    randomizer: Option<Randomizer>,
+    seed: u64,
+    inputs: Vec<Option<Input>>,
+    games: Vec<Game>,
+}
+
+/// Two replays of a versus match, played back on one timeline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersusReplay {
+    pub left: Replay,
+    pub right: Replay,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplaySummary {
+    pub score: u64,
+    pub pieces: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplayError {
+    IllegalInput { index: usize, input: Option<Input> },
+    ChecksumMismatch,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub token: String,
+}
+
+const SCRIPT_TOKENS: [(&str, Input); 7] = [
+    ("L", Input::Left),
+    ("R", Input::Right),
+    ("SD", Input::SoftDrop),
+    ("CCW", Input::RotateLeft),
+    ("CW", Input::RotateRight),
+    ("HD", Input::HardDrop),
+    ("CLR", Input::ClearBoard),
+];
+
+impl Replay {
+    /// Parses whitespace separated input tokens such as `L L R CW HD`.
+    pub fn from_script(text: &str) -> Result<Vec<Input>, ParseError> {
+        let mut inputs = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            for token in line.split_whitespace() {
+                let input = SCRIPT_TOKENS
+                    .iter()
+                    .find(|(name, _)| *name == token)
+                    .map(|(_, input)| *input)
+                    .ok_or_else(|| ParseError {
+                        line: index + 1,
+                        token: token.to_string(),
+                    })?;
+                inputs.push(input);
+            }
+        }
+        Ok(inputs)
+    }
+
+    /// Writes one token per line, in the format read by `from_script`.
+    pub fn to_script(inputs: &[Input]) -> String {
+        let mut script = String::new();
+        for input in inputs {
+            let (name, _) = SCRIPT_TOKENS
+                .iter()
+                .find(|(_, scripted)| scripted == input)
+                .unwrap();
+            script.push_str(name);
+            script.push('\n');
+        }
+        script
+    }
+
+    /// Plays the inputs and keeps what `validate` will accept: an input the game
+    /// rejects, such as a press against a wall, is kept as no input, and inputs after
+    /// the game is over are dropped. Neither changes the recorded game.
+    pub fn record(randomizer: Randomizer, seed: u64, inputs: Vec<Option<Input>>) -> Replay {
+        let mut game = Game::with_seed(randomizer, seed);
+        let mut recorded = Vec::new();
+        for input in inputs {
+            if game.is_over() {
+                break;
+            }
+            match game.tick(input) {
+                Ok(_) => recorded.push(input),
+                Err(_) => recorded.push(None),
+            }
+        }
+        Replay {
+            randomizer,
+            seed,
+            inputs: recorded,
+            checksum: checksum(&game),
+            keyframes: RefCell::new(Keyframes::default()),
+        }
+    }
+
+    /// The game after the first `index` inputs, resuming from the closest keyframe.
+    pub fn state_at(&self, index: usize) -> Game {
+        let index = index.min(self.inputs.len());
+        let mut keyframes = self.keyframes.borrow_mut();
+        if keyframes.randomizer != Some(self.randomizer)
+            || keyframes.seed != self.seed
+            || keyframes.inputs != self.inputs
+        {
+            *keyframes = Keyframes {
+                randomizer: Some(self.randomizer),
+                seed: self.seed,
+                inputs: self.inputs.clone(),
+                games: vec![Game::with_seed(self.randomizer, self.seed)],
+            };
+        }
+
+        let nearest = (index / KEYFRAME_INTERVAL).min(keyframes.games.len() - 1);
+        let mut game = keyframes.games[nearest].clone();
+        for step in nearest * KEYFRAME_INTERVAL..index {
+            let _ = game.tick(self.inputs[step]);
+            if (step + 1) % KEYFRAME_INTERVAL == 0 {
+                keyframes.games.push(game.clone());
+            }
+        }
+        game
+    }
+
+    pub fn validate(&self) -> Result<ReplaySummary, ReplayError> {
+        let mut game = Game::with_seed(self.randomizer, self.seed);
+        for (index, input) in self.inputs.iter().enumerate() {
+            if game.tick(*input).is_err() {
+                return Err(ReplayError::IllegalInput {
+                    index,
+                    input: *input,
+                });
+            }
+        }
+        if checksum(&game) != self.checksum {
+            return Err(ReplayError::ChecksumMismatch);
+        }
+        Ok(ReplaySummary {
+            score: game.score(),
+            pieces: game.piece_history().len(),
+        })
+    }
+
+    /// The initial frame followed by the frame after each input.
+    pub fn frames(&self) -> impl Iterator<Item = Grid> + '_ {
+        let mut game = Game::with_seed(self.randomizer, self.seed);
+        let initial = game.frame();
+        std::iter::once(initial).chain(self.inputs.iter().map(move |input| {
+            let _ = game.tick(*input);
+            game.frame()
+        }))
+    }
+}
+
+impl VersusReplay {
+    /// Paired frames, tick by tick. The side that runs out of inputs first keeps
+    /// showing its final frame.
+    pub fn frames(&self) -> impl Iterator<Item = (Grid, Grid)> + '_ {
+        let len = self.left.inputs.len().max(self.right.inputs.len()) + 1;
+        hold_last(self.left.frames())
+            .zip(hold_last(self.right.frames()))
+            .take(len)
+    }
+}
+
+fn hold_last(frames: impl Iterator<Item = Grid>) -> impl Iterator<Item = Grid> {
+    let mut last = None;
+    frames
+        .map(Some)
+        .chain(std::iter::repeat(None))
+        .map(move |frame| {
+            if frame.is_some() {
+                last = frame;
+            }
+            last.unwrap()
+        })
+}
+
+impl PartialEq for Replay {
+    fn eq(&self, other: &Replay) -> bool {
+        self.randomizer == other.randomizer
+            && self.seed == other.seed
+            && self.inputs == other.inputs
+            && self.checksum == other.checksum
+    }
+}
+
+impl fmt::Debug for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Replay")
+            .field("randomizer", &self.randomizer)
+            .field("seed", &self.seed)
+            .field("inputs", &self.inputs)
+            .field("checksum", &self.checksum)
+            .finish()
+    }
+}
+
+// FNV-1a over the score and every block of the final grid.
+fn checksum(game: &Game) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |byte: u8| {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    };
+    game.score()
+        .to_le_bytes()
+        .iter()
+        .for_each(|byte| feed(*byte));
+    for row in game.grid.iter() {
+        for block in row.iter() {
+            feed(block.value);
+            feed(block.color.map_or(0, |color| color as u8 + 1));
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::PLAYGROUND_WIDTH;
+
+    fn inputs() -> Vec<Option<Input>> {
+        let mut inputs = vec![
+            Some(Input::Left),
+            None,
+            Some(Input::RotateRight),
+            Some(Input::HardDrop),
+        ];
+        inputs.extend(vec![None; 10]);
+        inputs
+    }
+
+    #[test]
+    fn validate_ok() {
+        let replay = Replay::record(Randomizer::Random, 7, inputs());
+        let summary = replay.validate().unwrap();
+        assert_eq!(summary.score, 0);
+        assert_eq!(summary.pieces, 3);
+    }
+
+    #[test]
+    fn record_rejected_inputs() {
+        let mut inputs = vec![Some(Input::Left); PLAYGROUND_WIDTH as usize];
+        inputs.extend(self::inputs());
+        let replay = Replay::record(Randomizer::Random, 7, inputs.clone());
+        assert_eq!(replay.inputs.len(), inputs.len());
+        assert!(replay.inputs.contains(&None));
+        assert!(replay.validate().is_ok());
+
+        let mut game = Game::with_seed(Randomizer::Random, 7);
+        for input in &inputs {
+            let _ = game.tick(*input);
+        }
+        assert_eq!(replay.checksum, checksum(&game));
+    }
+
+    #[test]
+    fn record_after_game_over() {
+        let inputs = vec![Some(Input::HardDrop); 200];
+        let replay = Replay::record(Randomizer::Random, 7, inputs);
+        assert!(replay.inputs.len() < 200);
+        assert!(replay.state_at(replay.inputs.len()).is_over());
+        assert!(replay.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_checksum_mismatch() {
+        let mut replay = Replay::record(Randomizer::Random, 7, inputs());
+        replay.checksum += 1;
+        assert_eq!(replay.validate(), Err(ReplayError::ChecksumMismatch));
+    }
+
+    #[test]
+    fn validate_illegal_input() {
+        let mut replay = Replay::record(Randomizer::Random, 7, inputs());
+        replay.inputs = vec![Some(Input::Left); PLAYGROUND_WIDTH as usize];
+        assert!(matches!(
+            replay.validate(),
+            Err(ReplayError::IllegalInput {
+                input: Some(Input::Left),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn state_at() {
+        let mut inputs = Vec::new();
+        for _ in 0..5 {
+            inputs.extend(self::inputs());
+            inputs.extend(vec![Some(Input::Right), None, Some(Input::RotateLeft)]);
+            inputs.extend(vec![None; 20]);
+        }
+        let replay = Replay::record(Randomizer::Bag, 11, inputs);
+
+        let mut game = Game::with_seed(Randomizer::Bag, 11);
+        let mut stepped = vec![game.debug_dump()];
+        for input in &replay.inputs {
+            let _ = game.tick(*input);
+            stepped.push(game.debug_dump());
+        }
+
+        for &index in &[150, 3, 0, 64, 129, 128, replay.inputs.len()] {
+            assert_eq!(replay.state_at(index).debug_dump(), stepped[index]);
+        }
+        assert_eq!(
+            replay.state_at(replay.inputs.len() + 10).debug_dump(),
+            stepped[replay.inputs.len()]
+        );
+    }
+
+    #[test]
+    fn state_at_new_seed() {
+        let mut replay = Replay::record(Randomizer::Bag, 11, vec![None; 100]);
+        replay.state_at(100);
+
+        replay.seed = 12;
+        let mut game = Game::with_seed(Randomizer::Bag, 12);
+        for input in &replay.inputs[..70] {
+            let _ = game.tick(*input);
+        }
+        assert_eq!(replay.state_at(70).debug_dump(), game.debug_dump());
+
+        replay.randomizer = Randomizer::Random;
+        assert_eq!(
+            replay.state_at(0).debug_dump(),
+            Game::with_seed(Randomizer::Random, 12).debug_dump()
+        );
+    }
+
+    #[test]
+    fn script_round_trip() {
+        let inputs = Replay::from_script("L L R\nCW HD\n\nCCW SD CLR").unwrap();
+        assert_eq!(
+            inputs,
+            vec![
+                Input::Left,
+                Input::Left,
+                Input::Right,
+                Input::RotateRight,
+                Input::HardDrop,
+                Input::RotateLeft,
+                Input::SoftDrop,
+                Input::ClearBoard,
+            ]
+        );
+        let script = Replay::to_script(&inputs);
+        assert_eq!(script.lines().count(), inputs.len());
+        assert_eq!(Replay::from_script(&script), Ok(inputs));
+    }
+
+    #[test]
+    fn script_unknown_token() {
+        assert_eq!(
+            Replay::from_script("L R\nCW UP HD"),
+            Err(ParseError {
+                line: 2,
+                token: String::from("UP"),
+            })
+        );
+    }
+
+    #[test]
+    fn versus_frames() {
+        let left = Replay::record(Randomizer::Random, 7, inputs());
+        let mut longer = inputs();
+        longer.extend(vec![Some(Input::Right); 6]);
+        let right = Replay::record(Randomizer::Bag, 8, longer);
+        let versus = VersusReplay {
+            left: left.clone(),
+            right: right.clone(),
+        };
+
+        let frames = versus.frames().collect::<Vec<(Grid, Grid)>>();
+        assert_eq!(frames.len(), right.inputs.len() + 1);
+
+        let left_frames = left.frames().collect::<Vec<Grid>>();
+        let right_frames = right.frames().collect::<Vec<Grid>>();
+        for (tick, (left_frame, right_frame)) in frames.iter().enumerate() {
+            assert_eq!(*left_frame, left_frames[tick.min(left.inputs.len())]);
+            assert_eq!(*right_frame, right_frames[tick]);
+        }
+    }
+
+    #[test]
+    fn frames() {
+        let replay = Replay::record(Randomizer::Random, 7, inputs());
+        let frames = replay.frames().collect::<Vec<Grid>>();
+        assert_eq!(frames.len(), replay.inputs.len() + 1);
+        assert_eq!(frames[0], Game::with_seed(Randomizer::Random, 7).frame());
+        assert_ne!(frames[0], frames[1]);
+    }
+}
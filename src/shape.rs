@@ -1,11 +1,13 @@
 use crate::ui::Color;
 
+use std::borrow::Cow;
+
 use rand::{
     distributions::{Distribution, Standard},
     Rng,
 };
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Shape {
     O,
     I,
@@ -14,9 +16,30 @@ pub enum Shape {
     J,
     L,
     T,
+    Custom(CustomShape),
+}
+
+/// A non-standard piece. Each rotation is a 4x4 mask like the standard ones, so any
+/// piece up to four cells wide and tall fits, including every pentomino but the
+/// straight one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomShape {
+    pub name: String,
+    pub color: Color,
+    pub rotations: Vec<Rotation>,
 }
 
 impl Shape {
+    pub const STANDARD: [Shape; 7] = [
+        Shape::O,
+        Shape::I,
+        Shape::S,
+        Shape::Z,
+        Shape::J,
+        Shape::L,
+        Shape::T,
+    ];
+
     pub fn get_color(&self) -> Color {
         match self {
             Shape::O => Color::Blue,
@@ -26,18 +49,39 @@ impl Shape {
             Shape::J => Color::Magenta,
             Shape::L => Color::Red,
             Shape::T => Color::Green,
+            Shape::Custom(custom) => custom.color,
         }
     }
 
     pub fn get_possible_rotations(&self) -> Vec<Rotation> {
         match self {
-            Shape::O => vec![51],
-            Shape::I => vec![8738, 240],
-            Shape::S => vec![54, 561],
-            Shape::Z => vec![99, 306],
-            Shape::J => vec![275, 71, 802, 113],
-            Shape::L => vec![547, 116, 785, 23],
-            Shape::T => vec![114, 305, 39, 562],
+            Shape::Custom(custom) => custom.rotations.clone(),
+            _ => self
+                .standard_index()
+                .map_or_else(Vec::new, |index| ROTATIONS[index].to_vec()),
+        }
+    }
+
+    // Position in `STANDARD`, which also indexes `ROTATIONS` and `OFFSETS`.
+    fn standard_index(&self) -> Option<usize> {
+        match self {
+            Shape::O => Some(0),
+            Shape::I => Some(1),
+            Shape::S => Some(2),
+            Shape::Z => Some(3),
+            Shape::J => Some(4),
+            Shape::L => Some(5),
+            Shape::T => Some(6),
+            Shape::Custom(_) => None,
+        }
+    }
+
+    /// Playground column of the leftmost cell at spawn. The O sits one column further
+    /// right than the others so that every standard shape is centered.
+    pub fn spawn_column(&self) -> i32 {
+        match self {
+            Shape::O => 4,
+            _ => 3,
         }
     }
 
@@ -49,6 +93,81 @@ impl Shape {
             .map(|x| x.to_owned())
             .collect::<ShapeVec>()
     }
+
+    /// Checks that every rotation has four cells and that each one is the previous one
+    /// turned by 90 degrees, always in the same direction.
+    pub fn validate_rotations(&self) -> Result<(), String> {
+        for rotation in self.get_possible_rotations() {
+            let cells = self.rotation_coords(rotation).len();
+            if cells != 4 {
+                return Err(format!("rotation {} has {} cells", rotation, cells));
+            }
+        }
+        self.validate_playable()
+    }
+
+    /// Checks what any dealt shape needs, whatever its size: at least one rotation, the
+    /// same nonzero number of cells in each, and successive 90 degree turns.
+    pub fn validate_playable(&self) -> Result<(), String> {
+        let rotations = self.get_possible_rotations();
+        let cells = match rotations.first() {
+            Some(first) => self.rotation_coords(*first).len(),
+            None => return Err(String::from("no rotations")),
+        };
+        for rotation in rotations.iter() {
+            let count = self.rotation_coords(*rotation).len();
+            if count == 0 || count != cells {
+                return Err(format!("rotation {} has {} cells", rotation, count));
+            }
+        }
+        let turned = |cells: &[(i32, i32)], clockwise: bool| {
+            let turned = cells
+                .iter()
+                .map(|&(row, col)| if clockwise { (col, -row) } else { (-col, row) })
+                .collect::<Vec<(i32, i32)>>();
+            normalized(&turned)
+        };
+        let consistent = [true, false].iter().any(|&clockwise| {
+            rotations.iter().enumerate().all(|(index, rotation)| {
+                let next = rotations[(index + 1) % rotations.len()];
+                turned(&self.rotation_coords(*rotation), clockwise)
+                    == normalized(&self.rotation_coords(next))
+            })
+        });
+        if !consistent {
+            return Err(format!(
+                "rotations {:?} are not successive 90 degree turns",
+                rotations
+            ));
+        }
+        Ok(())
+    }
+
+    /// Same as `rotation_coords`, but read from a table built at compile time for the
+    /// standard rotations, indexed by shape and then by its at most four rotations.
+    pub fn offsets(&self, rotation: Rotation) -> Cow<'static, [(i32, i32)]> {
+        let found = self.standard_index().and_then(|shape| {
+            let index = ROTATIONS[shape].iter().position(|&mask| mask == rotation)?;
+            Some(&OFFSETS[shape][index])
+        });
+        match found {
+            Some(offsets) => Cow::Borrowed(offsets),
+            None => Cow::Owned(self.rotation_coords(rotation)),
+        }
+    }
+
+    /// Filled `(row, col)` cells of a rotation within its 4x4 box, row by row.
+    pub fn rotation_coords(&self, rotation: Rotation) -> Vec<(i32, i32)> {
+        let mut coords = Vec::new();
+        for (rowidx, row) in self.to_4x4(rotation).into_iter().enumerate() {
+            for (colidx, column) in row.into_iter().enumerate() {
+                if column != 0 {
+                    coords.push((rowidx as i32, colidx as i32));
+                }
+            }
+        }
+        coords
+    }
 }
 
 impl Distribution<Shape> for Standard {
@@ -66,6 +185,61 @@ impl Distribution<Shape> for Standard {
 }
 
 pub type Rotation = u16;
+
+// Shifts cells so the smallest row and column are zero, then sorts them.
+fn normalized(cells: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    let top = cells.iter().map(|cell| cell.0).min().unwrap_or(0);
+    let left = cells.iter().map(|cell| cell.1).min().unwrap_or(0);
+    let mut shifted = cells
+        .iter()
+        .map(|&(row, col)| (row - top, col - left))
+        .collect::<Vec<(i32, i32)>>();
+    shifted.sort_unstable();
+    shifted
+}
+
+const fn mask_offsets(rotation: Rotation) -> [(i32, i32); 4] {
+    let mut offsets = [(0, 0); 4];
+    let mut filled = 0;
+    let mut i: i32 = 0;
+    while i < 16 {
+        if (rotation >> (15 - i)) & 1 != 0 && filled < 4 {
+            offsets[filled] = (i / 4, i % 4);
+            filled += 1;
+        }
+        i += 1;
+    }
+    offsets
+}
+
+// The rotations of each standard shape, in `Shape::STANDARD` order.
+const ROTATIONS: [&[Rotation]; 7] = [
+    &[51],
+    &[8738, 240],
+    &[54, 561],
+    &[99, 306],
+    &[275, 71, 802, 113],
+    &[547, 116, 785, 23],
+    &[114, 305, 39, 562],
+];
+
+// Offsets of each standard shape's rotations, indexed like `ROTATIONS`.
+static OFFSETS: [[[(i32, i32); 4]; 4]; 7] = offsets_table();
+
+const fn offsets_table() -> [[[(i32, i32); 4]; 4]; 7] {
+    let mut table = [[[(0, 0); 4]; 4]; 7];
+    let mut shape = 0;
+    while shape < ROTATIONS.len() {
+        let mut index = 0;
+        while index < ROTATIONS[shape].len() {
+            table[shape][index] = mask_offsets(ROTATIONS[shape][index]);
+            index += 1;
+        }
+        shape += 1;
+    }
+    table
+}
+
 type ShapeVec = Vec<Vec<Rotation>>;
 
 #[cfg(test)]
@@ -239,4 +413,95 @@ mod tests {
             assert_eq!(shape.to_4x4(possible_rotation), *exp);
         }
     }
+
+    #[test]
+    fn rotation_coords() {
+        assert_eq!(
+            Shape::O.rotation_coords(51),
+            vec![(2, 2), (2, 3), (3, 2), (3, 3)]
+        );
+        assert_eq!(
+            Shape::I.rotation_coords(8738),
+            vec![(0, 2), (1, 2), (2, 2), (3, 2)]
+        );
+    }
+
+    #[test]
+    fn offsets() {
+        for shape in Shape::STANDARD.iter() {
+            for rotation in shape.get_possible_rotations() {
+                assert!(matches!(shape.offsets(rotation), Cow::Borrowed(_)));
+                assert_eq!(*shape.offsets(rotation), *shape.rotation_coords(rotation));
+            }
+        }
+        for (index, shape) in Shape::STANDARD.iter().enumerate() {
+            assert_eq!(shape.standard_index(), Some(index));
+        }
+        assert!(matches!(Shape::T.offsets(51), Cow::Owned(_)));
+    }
+
+    #[test]
+    fn validate_rotations() {
+        for shape in Shape::STANDARD.iter() {
+            assert_eq!(shape.validate_rotations(), Ok(()), "{:?}", shape);
+        }
+
+        let dot = Shape::Custom(CustomShape {
+            name: String::from("dot"),
+            color: Color::Red,
+            rotations: vec![32],
+        });
+        assert!(dot.validate_rotations().is_err());
+
+        // L orientations listed out of turning order.
+        let skipping = Shape::Custom(CustomShape {
+            name: String::from("skipping"),
+            color: Color::Red,
+            rotations: vec![547, 785, 116],
+        });
+        assert!(skipping.validate_rotations().is_err());
+    }
+
+    #[test]
+    fn validate_playable() {
+        let custom = |rotations: Vec<Rotation>| {
+            Shape::Custom(CustomShape {
+                name: String::from("custom"),
+                color: Color::Red,
+                rotations,
+            })
+        };
+        assert_eq!(custom(vec![32]).validate_playable(), Ok(()));
+        // The P pentomino turning clockwise.
+        assert_eq!(
+            custom(vec![52352, 58880, 19648, 52736]).validate_playable(),
+            Ok(())
+        );
+        assert_eq!(
+            custom(vec![]).validate_playable(),
+            Err(String::from("no rotations"))
+        );
+        assert!(custom(vec![0]).validate_playable().is_err());
+        assert!(custom(vec![32, 51]).validate_playable().is_err());
+    }
+
+    #[test]
+    fn custom() {
+        let shape = Shape::Custom(CustomShape {
+            name: String::from("dot"),
+            color: Color::Red,
+            rotations: vec![32],
+        });
+        assert_eq!(shape.get_color(), Color::Red);
+        assert_eq!(shape.get_possible_rotations(), vec![32]);
+        assert_eq!(
+            shape.to_4x4(32),
+            vec![
+                vec![0, 0, 0, 0],
+                vec![0, 0, 0, 0],
+                vec![0, 0, 1, 0],
+                vec![0, 0, 0, 0],
+            ]
+        );
+    }
 }
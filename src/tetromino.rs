@@ -1,4 +1,4 @@
-use crate::core::{Coord, Direction, Grid, PLAYGROUND_HEIGHT, PLAYGROUND_WIDTH};
+use crate::core::{Coord, Direction, GravityDir, Grid, PLAYGROUND_HEIGHT, PLAYGROUND_WIDTH};
 use crate::shape::{Rotation, Shape};
 use crate::ui::Color;
 use rand::prelude::SliceRandom;
@@ -13,77 +13,232 @@ pub struct Tetromino {
     pub color: Color,
     pub topleft: Coord,
     pub current_rotation: Rotation,
+    pub horizontal_wrap: bool,
+    /// Which way `move_down` moves the tetromino.
+    pub gravity: GravityDir,
 }
 
 impl Tetromino {
     pub fn new(grid: Grid) -> Tetromino {
-        let shape = rand::random::<Shape>();
+        Tetromino::with_shape(grid, rand::random::<Shape>())
+    }
+
+    pub fn with_shape(grid: Grid, shape: Shape) -> Tetromino {
         let current_rotation = shape
             .get_possible_rotations()
             .choose(&mut rand::thread_rng())
             .copied()
             .unwrap();
+        Tetromino::with_rotation(grid, shape, current_rotation)
+    }
+
+    pub fn with_rotation(grid: Grid, shape: Shape, current_rotation: Rotation) -> Tetromino {
         let color = shape.get_color();
+        let tetrovec = shape.to_4x4(current_rotation);
+        let leftmost = (0..4)
+            .find(|&col| tetrovec.iter().any(|row| row[col] != 0))
+            .unwrap_or(0) as i32;
+        let x = shape.spawn_column() - leftmost;
         Tetromino {
             grid,
             shape,
             color,
             current_rotation,
-            topleft: Coord {
-                y: 0,
-                x: PLAYGROUND_WIDTH / 2 - 1,
-            },
+            topleft: Coord { y: 0, x },
+            horizontal_wrap: false,
+            gravity: GravityDir::Down,
         }
     }
 
-    pub fn move_sideways(&mut self, direction: Direction) -> Result<(), &'static str> {
-        let tetrovec = self.shape.to_4x4(self.current_rotation);
-        for (rowidx, row) in tetrovec.into_iter().enumerate() {
-            for (colidx, column) in row.into_iter().enumerate() {
-                if column != 0 {
-                    let Coord { y, x } = self.topleft;
-                    let next_step = colidx as i32 + x + direction as i32;
-                    if !(0..PLAYGROUND_WIDTH).contains(&next_step) {
-                        return Err("Out of bounds.");
+    fn wrap_column(&self, x: i32) -> i32 {
+        if self.horizontal_wrap {
+            x.rem_euclid(PLAYGROUND_WIDTH)
+        } else {
+            x
+        }
+    }
+
+    /// Grid coordinates of the filled cells.
+    pub fn cells(&self) -> Vec<Coord> {
+        self.cells_for_rotation(self.current_rotation)
+    }
+
+    /// Grid coordinates the tetromino would cover in `rotation` at its current topleft.
+    pub fn cells_for_rotation(&self, rotation: Rotation) -> Vec<Coord> {
+        let Coord { y, x } = self.topleft;
+        self.shape
+            .offsets(rotation)
+            .iter()
+            .map(|&(row, col)| Coord {
+                y: y + row,
+                x: self.wrap_column(x + col),
+            })
+            .collect()
+    }
+
+    /// Top-left and bottom-right grid coordinates of the filled cells, or `None` for a
+    /// custom rotation without any.
+    pub fn bounding_box(&self) -> Option<(Coord, Coord)> {
+        let cells = self.cells();
+        let ys = cells.iter().map(|cell| cell.y);
+        let xs = cells.iter().map(|cell| cell.x);
+        Some((
+            Coord {
+                y: ys.clone().min()?,
+                x: xs.clone().min()?,
+            },
+            Coord {
+                y: ys.max()?,
+                x: xs.max()?,
+            },
+        ))
+    }
+
+    /// Where the tetromino can land by rotating in place, sliding sideways and dropping.
+    pub fn placements(&self) -> Vec<(Rotation, Coord)> {
+        let mut placements = Vec::new();
+        for rotation in self.shape.get_possible_rotations() {
+            let mut rotated = self.clone();
+            rotated.current_rotation = rotation;
+            if !rotated.fits() {
+                continue;
+            }
+            for direction in [Direction::Left, Direction::Right].iter() {
+                let mut shifted = rotated.clone();
+                for _ in 0..PLAYGROUND_WIDTH {
+                    let mut dropped = shifted.clone();
+                    dropped.move_all_the_way_down();
+                    if !placements.contains(&(rotation, dropped.topleft)) {
+                        placements.push((rotation, dropped.topleft));
                     }
-                    if self.grid[rowidx + y as usize][next_step as usize].value != 0 {
-                        return Err("Collision.");
+                    if shifted.move_sideways(*direction).is_err() {
+                        break;
                     }
                 }
             }
         }
-        self.topleft.x += direction as i32;
+        placements
+    }
+
+    /// Whether landing here would leave an empty cell right under one of its cells.
+    pub fn covers_hole(&self) -> bool {
+        let cells = self.cells();
+        cells.iter().any(|cell| {
+            let below = Coord {
+                y: cell.y + 1,
+                x: cell.x,
+            };
+            Tetromino::in_bounds(below)
+                && !cells.contains(&below)
+                && self.grid[below.y as usize][below.x as usize].value == 0
+        })
+    }
+
+    // Guards every grid lookup, so a corrupted topleft is reported instead of panicking.
+    fn in_bounds(cell: Coord) -> bool {
+        (0..PLAYGROUND_WIDTH).contains(&cell.x) && (0..PLAYGROUND_HEIGHT).contains(&cell.y)
+    }
 
+    fn check(&self, rotation: Rotation, topleft: Coord) -> Result<(), &'static str> {
+        for &(row, col) in self.shape.offsets(rotation).iter() {
+            let cell = Coord {
+                y: topleft.y + row,
+                x: self.wrap_column(topleft.x + col),
+            };
+            if !Tetromino::in_bounds(cell) {
+                return Err("Out of bounds.");
+            }
+            if self.grid[cell.y as usize][cell.x as usize].value != 0 {
+                return Err("Collision.");
+            }
+        }
         Ok(())
     }
 
+    pub fn fits(&self) -> bool {
+        self.check(self.current_rotation, self.topleft).is_ok()
+    }
+
+    pub fn move_sideways(&mut self, direction: Direction) -> Result<(), &'static str> {
+        let next_step = Coord {
+            y: self.topleft.y,
+            x: self.topleft.x + direction as i32,
+        };
+        self.check(self.current_rotation, next_step)?;
+        self.topleft.x = self.wrap_column(next_step.x);
+
+        Ok(())
+    }
+
+    /// Moves the tetromino to the edge opposite its gravity, keeping its position along
+    /// that edge, or centered on it for sideways gravity.
+    pub fn move_to_entry(&mut self) {
+        let Some((min, max)) = self.bounding_box() else {
+            return;
+        };
+        let centered = (PLAYGROUND_HEIGHT - (max.y - min.y + 1)) / 2 - min.y + self.topleft.y;
+        match self.gravity {
+            GravityDir::Down => self.topleft.y = 0,
+            GravityDir::Up => self.topleft.y += PLAYGROUND_HEIGHT - 1 - max.y,
+            GravityDir::Left => {
+                self.topleft.x += PLAYGROUND_WIDTH - 1 - max.x;
+                self.topleft.y = centered;
+            }
+            GravityDir::Right => {
+                self.topleft.x -= min.x;
+                self.topleft.y = centered;
+            }
+        }
+    }
+
+    /// Whether the tetromino still touches the edge it entered from.
+    pub fn at_entry(&self) -> bool {
+        let cells = self.cells();
+        match self.gravity {
+            GravityDir::Down => self.topleft.y <= 0,
+            GravityDir::Up => cells.iter().any(|cell| cell.y >= PLAYGROUND_HEIGHT - 1),
+            GravityDir::Left => cells.iter().any(|cell| cell.x >= PLAYGROUND_WIDTH - 1),
+            GravityDir::Right => cells.iter().any(|cell| cell.x <= 0),
+        }
+    }
+
+    /// Whether the tetromino rests on the floor or on landed blocks.
+    pub fn is_grounded(&self) -> bool {
+        self.clone().move_down().is_err()
+    }
+
     pub fn move_all_the_way_down(&mut self) {
         while let Ok(()) = self.move_down() {
             continue;
         }
     }
 
+    /// Moves the tetromino one cell along its gravity.
     pub fn move_down(&mut self) -> Result<(), &'static str> {
-        let tetrovec = self.shape.to_4x4(self.current_rotation);
-        for (rowidx, row) in tetrovec.into_iter().enumerate() {
-            for (colidx, column) in row.into_iter().enumerate() {
-                if column != 0 {
-                    let Coord { y, x } = self.topleft;
-                    let next_step = Coord {
-                        y: rowidx as i32 + y + 1,
-                        x: colidx as i32 + x,
-                    };
-                    if next_step.y >= PLAYGROUND_HEIGHT {
-                        return Err("Out of bounds.");
-                    }
-                    if self.grid[next_step.y as usize][next_step.x as usize].value != 0 {
-                        return Err("Collision.");
-                    }
-                }
-            }
-        }
-        self.topleft.y += 1;
+        let (dy, dx) = match self.gravity {
+            GravityDir::Down => (1, 0),
+            GravityDir::Up => (-1, 0),
+            GravityDir::Left => (0, -1),
+            GravityDir::Right => (0, 1),
+        };
+        let next_step = Coord {
+            y: self.topleft.y + dy,
+            x: self.topleft.x + dx,
+        };
+        self.check(self.current_rotation, next_step)?;
+        self.topleft = next_step;
+
+        Ok(())
+    }
 
+    /// Jumps straight to `target`, which must be one of the shape's rotations and fit
+    /// where the tetromino is.
+    pub fn rotate_to(&mut self, target: Rotation) -> Result<(), &'static str> {
+        if !self.shape.get_possible_rotations().contains(&target) {
+            return Err("Invalid rotation.");
+        }
+        self.check(target, self.topleft)?;
+        self.current_rotation = target;
         Ok(())
     }
 
@@ -92,33 +247,13 @@ impl Tetromino {
         let current_index = rotations
             .iter()
             .position(|x| *x == self.current_rotation)
-            .unwrap();
+            .ok_or("Invalid rotation.")?;
         let next_index = i32::checked_rem_euclid(
             current_index as i32 + direction as i32,
             rotations.len() as i32,
         );
         let potential_rotation = rotations[next_index.unwrap() as usize];
-        let tetrovec = self.shape.to_4x4(potential_rotation);
-        for (rowidx, row) in tetrovec.into_iter().enumerate() {
-            for (colidx, column) in row.into_iter().enumerate() {
-                if column != 0 {
-                    let Coord { y, x } = self.topleft;
-                    let next_step = Coord {
-                        y: rowidx as i32 + y,
-                        x: colidx as i32 + x,
-                    };
-                    if !(0..PLAYGROUND_WIDTH).contains(&next_step.x) {
-                        return Err("Out of bounds.");
-                    }
-                    if next_step.y >= PLAYGROUND_HEIGHT {
-                        return Err("Out of bounds.");
-                    }
-                    if self.grid[next_step.y as usize][next_step.x as usize].value != 0 {
-                        return Err("Collision.");
-                    }
-                }
-            }
-        }
+        self.check(potential_rotation, self.topleft)?;
         self.current_rotation = potential_rotation;
         Ok(())
     }
@@ -128,6 +263,7 @@ impl Tetromino {
 mod tests {
     use super::*;
     use crate::core::{Block, Game};
+    use crate::shape::CustomShape;
     use rstest::*;
 
     #[fixture]
@@ -151,6 +287,49 @@ mod tests {
     )]
     fn all_shapes(shape: Shape) {}
 
+    #[rstest(
+        shape,
+        rotation,
+        columns,
+        case(Shape::O, 51, vec![4, 5]),
+        case(Shape::I, 240, vec![3, 4, 5, 6]),
+        case(Shape::S, 54, vec![3, 4, 5]),
+        case(Shape::Z, 99, vec![3, 4, 5]),
+        case(Shape::J, 71, vec![3, 4, 5]),
+        case(Shape::L, 23, vec![3, 4, 5]),
+        case(Shape::T, 39, vec![3, 4, 5])
+    )]
+    fn spawn_column(shape: Shape, rotation: Rotation, columns: Vec<i32>) {
+        let grid = Game::create_grid();
+        let tetromino = Tetromino::with_rotation(grid, shape.clone(), rotation);
+        let mut spawned = tetromino
+            .cells()
+            .iter()
+            .map(|cell| cell.x)
+            .collect::<Vec<i32>>();
+        spawned.sort_unstable();
+        spawned.dedup();
+        assert_eq!(spawned, columns);
+
+        for rotation in shape.get_possible_rotations() {
+            let tetromino = Tetromino::with_rotation(grid, shape.clone(), rotation);
+            let leftmost = tetromino.cells().iter().map(|cell| cell.x).min();
+            assert_eq!(leftmost, Some(shape.spawn_column()));
+        }
+    }
+
+    #[apply(all_shapes)]
+    fn fits(mut tetromino: Tetromino, shape: Shape) {
+        tetromino.shape = shape;
+        assert!(tetromino.fits());
+        tetromino.topleft.x = PLAYGROUND_WIDTH;
+        assert!(!tetromino.fits());
+        tetromino.topleft.x = 5;
+        tetromino.grid =
+            [[Block::new(1, None); PLAYGROUND_WIDTH as usize]; PLAYGROUND_HEIGHT as usize];
+        assert!(!tetromino.fits());
+    }
+
     #[apply(all_shapes)]
     fn move_sideways_left_ok(mut tetromino: Tetromino, shape: Shape) {
         tetromino.shape = shape;
@@ -213,6 +392,235 @@ mod tests {
         assert_eq!(tetromino.move_sideways(Direction::Left), Err("Collision."));
     }
 
+    #[apply(all_shapes)]
+    fn move_sideways_wrap(mut tetromino: Tetromino, shape: Shape) {
+        tetromino.shape = shape;
+        tetromino.horizontal_wrap = true;
+        let columns = |tetromino: &Tetromino| {
+            let mut columns = tetromino
+                .cells()
+                .iter()
+                .map(|cell| cell.x)
+                .collect::<Vec<i32>>();
+            columns.sort_unstable();
+            columns
+        };
+        let before = columns(&tetromino);
+
+        for _ in 0..PLAYGROUND_WIDTH {
+            assert_eq!(tetromino.move_sideways(Direction::Right), Ok(()));
+            assert!(tetromino
+                .cells()
+                .iter()
+                .all(|cell| (0..PLAYGROUND_WIDTH).contains(&cell.x)));
+        }
+        assert_eq!(columns(&tetromino), before);
+    }
+
+    #[test]
+    fn move_sideways_wrap_seam() {
+        let grid = Game::create_grid();
+        let mut tetromino = Tetromino::with_rotation(grid, Shape::I, 240);
+        tetromino.horizontal_wrap = true;
+        tetromino.topleft = Coord {
+            y: 0,
+            x: PLAYGROUND_WIDTH - 4,
+        };
+
+        assert_eq!(tetromino.move_sideways(Direction::Right), Ok(()));
+        assert_eq!(tetromino.move_sideways(Direction::Right), Ok(()));
+        let mut columns = tetromino
+            .cells()
+            .iter()
+            .map(|cell| cell.x)
+            .collect::<Vec<i32>>();
+        columns.sort_unstable();
+        assert_eq!(
+            columns,
+            vec![0, 1, PLAYGROUND_WIDTH - 2, PLAYGROUND_WIDTH - 1]
+        );
+        assert!(tetromino.fits());
+
+        tetromino.grid[2][2] = Block::new(1, None);
+        assert_eq!(tetromino.move_sideways(Direction::Right), Err("Collision."));
+        tetromino.grid[2][2] = Block::new(0, None);
+        tetromino.grid[2][0] = Block::new(1, None);
+        assert!(!tetromino.fits());
+    }
+
+    #[apply(all_shapes)]
+    fn corrupted_topleft(mut tetromino: Tetromino, shape: Shape) {
+        tetromino.shape = shape;
+        tetromino.current_rotation = tetromino.shape.get_possible_rotations()[0];
+        for &topleft in &[
+            Coord { y: -20, x: 3 },
+            Coord { y: 3, x: -20 },
+            Coord { y: 40, x: 3 },
+            Coord { y: 3, x: 40 },
+        ] {
+            tetromino.topleft = topleft;
+            assert!(!tetromino.fits());
+            assert_eq!(
+                tetromino.move_sideways(Direction::Left),
+                Err("Out of bounds.")
+            );
+            assert_eq!(
+                tetromino.move_sideways(Direction::Right),
+                Err("Out of bounds.")
+            );
+            assert_eq!(tetromino.move_down(), Err("Out of bounds."));
+            tetromino.move_all_the_way_down();
+            assert_eq!(tetromino.topleft, topleft);
+        }
+        tetromino.current_rotation = 1;
+        assert_eq!(tetromino.rotate(Direction::Left), Err("Invalid rotation."));
+    }
+
+    #[apply(all_shapes)]
+    fn is_grounded(mut tetromino: Tetromino, shape: Shape) {
+        tetromino.shape = shape;
+        tetromino.current_rotation = tetromino.shape.get_possible_rotations()[0];
+        assert!(!tetromino.is_grounded());
+
+        let airborne = tetromino.topleft;
+        tetromino.move_all_the_way_down();
+        let floor_rest = tetromino.topleft;
+        assert!(tetromino.is_grounded());
+        assert_eq!(tetromino.topleft, floor_rest);
+
+        tetromino.topleft = airborne;
+        for Coord { y, x } in tetromino.cells() {
+            tetromino.grid[y as usize + 1][x as usize] = Block::new(1, None);
+        }
+        assert!(tetromino.is_grounded());
+        assert_ne!(tetromino.topleft, floor_rest);
+    }
+
+    #[apply(all_shapes)]
+    fn cells_for_rotation(mut tetromino: Tetromino, shape: Shape) {
+        tetromino.shape = shape;
+        tetromino.current_rotation = tetromino.shape.get_possible_rotations()[0];
+        for _ in tetromino.shape.get_possible_rotations() {
+            let rotated = {
+                let mut rotated = tetromino.clone();
+                rotated.rotate(Direction::Right).unwrap();
+                rotated
+            };
+            let before = tetromino.current_rotation;
+            let preview = tetromino.cells_for_rotation(rotated.current_rotation);
+            assert_eq!(tetromino.current_rotation, before);
+            assert_eq!(preview, rotated.cells());
+            tetromino = rotated;
+        }
+    }
+
+    #[rstest]
+    fn cells_for_rotation_pentomino(mut tetromino: Tetromino) {
+        tetromino.shape = Shape::Custom(CustomShape {
+            name: String::from("plus"),
+            color: Color::Red,
+            rotations: vec![0x4e40],
+        });
+        assert_eq!(tetromino.cells_for_rotation(0x4e40).len(), 5);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let grid = Game::create_grid();
+        let mut tetromino = Tetromino::with_rotation(grid, Shape::I, 240);
+        tetromino.topleft = Coord { y: 5, x: 2 };
+        assert_eq!(
+            tetromino.bounding_box(),
+            Some((Coord { y: 7, x: 2 }, Coord { y: 7, x: 5 }))
+        );
+
+        tetromino.current_rotation = 8738;
+        assert_eq!(
+            tetromino.bounding_box(),
+            Some((Coord { y: 5, x: 4 }, Coord { y: 8, x: 4 }))
+        );
+
+        tetromino.shape = Shape::Custom(CustomShape {
+            name: String::from("empty"),
+            color: Color::Red,
+            rotations: vec![0],
+        });
+        tetromino.current_rotation = 0;
+        assert_eq!(tetromino.bounding_box(), None);
+    }
+
+    #[test]
+    fn covers_hole() {
+        let mut grid = Game::create_grid();
+        grid[PLAYGROUND_HEIGHT as usize - 1][3] = Block::new(1, None);
+        grid[PLAYGROUND_HEIGHT as usize - 1][5] = Block::new(1, None);
+        let mut tetromino = Tetromino::with_rotation(grid, Shape::T, 114);
+        tetromino.move_all_the_way_down();
+        assert!(!tetromino.covers_hole());
+
+        tetromino.topleft = Coord { y: 0, x: 0 };
+        tetromino.move_all_the_way_down();
+        assert!(tetromino.covers_hole());
+    }
+
+    #[test]
+    fn rotate_to() {
+        let grid = Game::create_grid();
+        let mut tetromino = Tetromino::with_rotation(grid, Shape::L, 547);
+        tetromino.topleft = Coord { y: 5, x: 5 };
+        assert_eq!(tetromino.rotate_to(785), Ok(()));
+        assert_eq!(tetromino.current_rotation, 785);
+
+        assert_eq!(tetromino.rotate_to(51), Err("Invalid rotation."));
+        assert_eq!(tetromino.current_rotation, 785);
+
+        tetromino.grid =
+            [[Block::new(1, None); PLAYGROUND_WIDTH as usize]; PLAYGROUND_HEIGHT as usize];
+        assert_eq!(tetromino.rotate_to(23), Err("Collision."));
+        assert_eq!(tetromino.current_rotation, 785);
+    }
+
+    // The 4x4 matrix walk that the offset table replaced.
+    fn matrix_fits(tetromino: &Tetromino) -> bool {
+        let tetrovec = tetromino.shape.to_4x4(tetromino.current_rotation);
+        for (rowidx, row) in tetrovec.into_iter().enumerate() {
+            for (colidx, column) in row.into_iter().enumerate() {
+                if column != 0 {
+                    let y = rowidx as i32 + tetromino.topleft.y;
+                    let x = colidx as i32 + tetromino.topleft.x;
+                    if !(0..PLAYGROUND_WIDTH).contains(&x) || !(0..PLAYGROUND_HEIGHT).contains(&y) {
+                        return false;
+                    }
+                    if tetromino.grid[y as usize][x as usize].value != 0 {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    #[apply(all_shapes)]
+    fn offsets_match_matrix(mut tetromino: Tetromino, shape: Shape) {
+        tetromino.shape = shape;
+        for (rowidx, row) in tetromino.grid.iter_mut().enumerate().skip(8) {
+            for (colidx, block) in row.iter_mut().enumerate() {
+                if (rowidx * 3 + colidx) % 4 == 0 {
+                    *block = Block::new(1, None);
+                }
+            }
+        }
+        for rotation in tetromino.shape.get_possible_rotations() {
+            tetromino.current_rotation = rotation;
+            for y in -4..PLAYGROUND_HEIGHT + 1 {
+                for x in -4..PLAYGROUND_WIDTH + 1 {
+                    tetromino.topleft = Coord { y, x };
+                    assert_eq!(tetromino.fits(), matrix_fits(&tetromino));
+                }
+            }
+        }
+    }
+
     #[apply(all_shapes)]
     fn move_down_no_obstacles(mut tetromino: Tetromino, shape: Shape) {
         tetromino.shape = shape;
@@ -238,6 +646,33 @@ mod tests {
         assert_eq!(tetromino.move_down(), Err("Collision."));
     }
 
+    #[rstest(
+        gravity,
+        step,
+        case(GravityDir::Down, Coord { y: 1, x: 0 }),
+        case(GravityDir::Up, Coord { y: -1, x: 0 }),
+        case(GravityDir::Left, Coord { y: 0, x: -1 }),
+        case(GravityDir::Right, Coord { y: 0, x: 1 })
+    )]
+    fn move_down_gravity(mut tetromino: Tetromino, gravity: GravityDir, step: Coord) {
+        tetromino.gravity = gravity;
+        let Coord { y, x } = tetromino.topleft;
+        tetromino.move_down().unwrap();
+        assert_eq!(
+            tetromino.topleft,
+            Coord {
+                y: y + step.y,
+                x: x + step.x
+            }
+        );
+
+        tetromino.move_to_entry();
+        assert!(tetromino.at_entry());
+        tetromino.move_all_the_way_down();
+        assert!(!tetromino.at_entry());
+        assert!(tetromino.is_grounded());
+    }
+
     #[apply(all_shapes)]
     fn rotate_left_ok(mut tetromino: Tetromino, shape: Shape) {
         tetromino.shape = shape;
@@ -258,12 +693,9 @@ mod tests {
         let possible_rotations = tetromino.shape.get_possible_rotations();
         tetromino.current_rotation = possible_rotations[0];
 
-        for rotation_index in 1..possible_rotations.len() {
+        for rotation in possible_rotations.iter().skip(1) {
             assert_eq!(tetromino.rotate(Direction::Right), Ok(()));
-            assert_eq!(
-                tetromino.current_rotation,
-                possible_rotations[rotation_index]
-            )
+            assert_eq!(tetromino.current_rotation, *rotation)
         }
     }
 
@@ -276,7 +708,7 @@ mod tests {
         for rotation in possible_rotations {
             tetromino.current_rotation = rotation;
             assert_eq!(tetromino.rotate(Direction::Left), Err("Out of bounds."));
-            assert_eq!(tetromino.current_rotation, tetromino.current_rotation);
+            assert_eq!(tetromino.current_rotation, rotation);
         }
     }
 
@@ -289,7 +721,7 @@ mod tests {
         for rotation in possible_rotations {
             tetromino.current_rotation = rotation;
             assert_eq!(tetromino.rotate(Direction::Right), Err("Out of bounds."));
-            assert_eq!(tetromino.current_rotation, tetromino.current_rotation);
+            assert_eq!(tetromino.current_rotation, rotation);
         }
     }
 
@@ -305,7 +737,7 @@ mod tests {
         for rotation in possible_rotations {
             tetromino.current_rotation = rotation;
             assert_eq!(tetromino.rotate(Direction::Left), Err("Collision."));
-            assert_eq!(tetromino.current_rotation, tetromino.current_rotation);
+            assert_eq!(tetromino.current_rotation, rotation);
         }
     }
 
@@ -321,7 +753,7 @@ mod tests {
         for rotation in possible_rotations {
             tetromino.current_rotation = rotation;
             assert_eq!(tetromino.rotate(Direction::Right), Err("Collision."));
-            assert_eq!(tetromino.current_rotation, tetromino.current_rotation);
+            assert_eq!(tetromino.current_rotation, rotation);
         }
     }
 }
@@ -2,7 +2,7 @@ use crate::core::{Coord, Grid, PLAYGROUND_HEIGHT, PLAYGROUND_WIDTH};
 use crate::tetromino::Tetromino;
 use ncurses as nc;
 use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
+use strum_macros::{Display, EnumIter, EnumString};
 
 const SCREEN_WIDTH: i32 = PLAYGROUND_WIDTH * 2;
 const SCREEN_HEIGHT: i32 = PLAYGROUND_HEIGHT;
@@ -62,21 +62,10 @@ pub fn refresh_screens(inner_screen: nc::WINDOW) {
 }
 
 pub fn draw_tetromino(screen: nc::WINDOW, tetromino: &Tetromino) {
-    let tetrovec = tetromino.shape.to_4x4(tetromino.current_rotation);
-    for (rowidx, row) in tetrovec.into_iter().enumerate() {
-        for (colidx, column) in row.into_iter().enumerate() {
-            if column != 0 {
-                let Coord { y, x } = tetromino.topleft;
-                nc::wattr_on(screen, nc::COLOR_PAIR(tetromino.color as i16));
-                nc::mvwaddstr(
-                    screen,
-                    rowidx as i32 + y as i32,
-                    (colidx as i32 + x as i32) * 2,
-                    "██",
-                );
-                nc::wattroff(screen, nc::COLOR_PAIR(tetromino.color as i16));
-            }
-        }
+    for Coord { y, x } in tetromino.cells() {
+        nc::wattr_on(screen, nc::COLOR_PAIR(tetromino.color as i16));
+        nc::mvwaddstr(screen, y, x * 2, "██");
+        nc::wattroff(screen, nc::COLOR_PAIR(tetromino.color as i16));
     }
 }
 
@@ -121,7 +110,8 @@ pub fn draw_score(score: u64) {
     nc::mvwaddstr(nc::stdscr(), y, x, &format!("SCORE: {}", score));
 }
 
-#[derive(Clone, Copy, Debug, EnumIter, PartialEq)]
+/// Parses from and displays as its name, e.g. "Blue".
+#[derive(Clone, Copy, Debug, Display, EnumIter, EnumString, PartialEq)]
 pub enum Color {
     Yellow = nc::COLOR_YELLOW as isize,
     Blue = nc::COLOR_BLUE as isize,
@@ -131,3 +121,87 @@ pub enum Color {
     Cyan = nc::COLOR_CYAN as isize,
     White = nc::COLOR_WHITE as isize,
 }
+
+/// Maps playground cells to screen positions and back. Curses cells are drawn two
+/// columns wide and one row high.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    origin_x: i32,
+    origin_y: i32,
+    cell_width: i32,
+    cell_height: i32,
+}
+
+impl Viewport {
+    /// Cells must be at least one column wide and one row high.
+    pub fn new(
+        origin_x: i32,
+        origin_y: i32,
+        cell_width: i32,
+        cell_height: i32,
+    ) -> Result<Viewport, &'static str> {
+        if cell_width < 1 || cell_height < 1 {
+            return Err("Invalid cell size.");
+        }
+        Ok(Viewport {
+            origin_x,
+            origin_y,
+            cell_width,
+            cell_height,
+        })
+    }
+
+    pub fn to_screen(&self, coord: Coord) -> (i32, i32) {
+        (
+            self.origin_x + coord.x * self.cell_width,
+            self.origin_y + coord.y * self.cell_height,
+        )
+    }
+
+    pub fn from_screen(&self, px: i32, py: i32) -> Coord {
+        Coord {
+            y: (py - self.origin_y).div_euclid(self.cell_height),
+            x: (px - self.origin_x).div_euclid(self.cell_width),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn color_name_round_trip() {
+        for color in Color::iter() {
+            assert_eq!(Color::from_str(&color.to_string()), Ok(color));
+        }
+        assert_eq!(Color::Blue.to_string(), "Blue");
+    }
+
+    #[test]
+    fn color_unknown_name() {
+        assert!(Color::from_str("Orange").is_err());
+        assert!(Color::from_str("blue").is_err());
+    }
+
+    #[test]
+    fn viewport_round_trip() {
+        let viewport = Viewport::new(3, 5, 2, 1).unwrap();
+        for &(y, x) in &[(0, 0), (15, 9), (7, 4), (-2, -1)] {
+            let coord = Coord { y, x };
+            let (px, py) = viewport.to_screen(coord);
+            assert_eq!(viewport.from_screen(px, py), coord);
+            assert_eq!(viewport.from_screen(px + 1, py), coord);
+            assert_eq!(viewport.from_screen(px, py + 1).y, coord.y + 1);
+        }
+        assert_eq!(viewport.to_screen(Coord { y: 1, x: 2 }), (7, 6));
+    }
+
+    #[test]
+    fn viewport_zero_size() {
+        assert_eq!(Viewport::new(3, 5, 0, 1), Err("Invalid cell size."));
+        assert_eq!(Viewport::new(3, 5, 2, 0), Err("Invalid cell size."));
+        assert_eq!(Viewport::new(3, 5, -2, 1), Err("Invalid cell size."));
+    }
+}